@@ -100,7 +100,7 @@ impl Asset{
         contract_addr: contract_addr.to_string(),
         msg: to_binary(&Cw20ExecuteMsg::Transfer {
           recipient: recipient.to_string(),
-          amount : amount
+          amount
         })?,
         funds: vec![],
       })),
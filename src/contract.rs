@@ -1,21 +1,53 @@
 use cosmwasm_std::{
     entry_point, to_binary, Coin, Deps, DepsMut, Env, MessageInfo, Response,from_binary,Binary,
-    StdResult, Uint128,CosmosMsg,WasmMsg,Decimal,BankMsg,Order,Pair
+    StdResult, Uint128,CosmosMsg,WasmMsg,Decimal,BankMsg,Order,Pair,Event,Fraction,Storage,QueryRequest,WasmQuery,StdError,
+    SubMsg,Reply,ContractResult
 };
 
 use cw2::set_contract_version;
-use cw20::{ Cw20ExecuteMsg,Cw20ReceiveMsg};
-use cw721::{Cw721ReceiveMsg, Cw721ExecuteMsg};
+use cw20::{ Cw20ExecuteMsg,Cw20ReceiveMsg,Cw20QueryMsg,BalanceResponse};
+use cw721::{Cw721ReceiveMsg, Cw721ExecuteMsg, Cw721QueryMsg, OwnerOfResponse};
 
 use crate::error::{ContractError};
-use crate::msg::{ ExecuteMsg, InstantiateMsg, QueryMsg,SellNft, BuyNft};
-use crate::state::{State,CONFIG,Offering, OFFERINGS,Asset,UserInfo, MEMBERS};
-use crate::package::{OfferingsResponse,QueryOfferingsResult};
+use crate::msg::{ ExecuteMsg, InstantiateMsg, QueryMsg,SellNft, BuyNft, ApprovalListing, Cw20HookMsg, Cw721HookMsg, ProposeSwap,
+    SubmitCollection, MarketplaceHookMsg, MarketplaceEvent, CollectionQueryMsg, MinterResponse};
+use crate::state::{State,CONFIG,Offering, OFFERINGS,Asset,Custody,UserInfo, MEMBERS, ConfigChange, PendingChange, PENDING_CHANGES,
+    FEE_TIERS, TRADER_VOLUMES, NftItem, Bundle, BundleItem, BUNDLES, CollectionInfo, COLLECTIONS, Swap, SWAPS,
+    DenomInfo, DENOMS, CollectionStatus, CURATORS, MODERATORS, FLAGGED_TOKENS, BLOCKED_ADDRESSES, FLAG_LOG,
+    FlagRecord, FlagTarget, REFERRERS, REFERRER_EARNINGS, Storefront, STOREFRONTS, HOOKS,
+    Settlement, SETTLEMENTS, LISTED_TOKENS, PendingReply, PENDING_REPLIES, REPLY_SEQ, CLAIMABLE,
+    CLAIMABLE_TOTALS, PROCEEDS, PROCEEDS_TOTALS, PULL_PROCEEDS};
+use crate::package::{OfferingsResponse,QueryOfferingsResult,PendingChangesResponse,QueryPendingChangeResult,
+    FeeForResponse, DenomsResponse, QueryDenomResult, CollectionsResponse, QueryCollectionResult, CuratorsResponse,
+    ModeratorsResponse, FlagLogResponse, QueryFlagResult, ReferrerResponse,
+    StorefrontsResponse, QueryStorefrontResult, HooksResponse,
+    ClaimableResponse, SolvencyResponse, FundsSolvency, ProceedsResponse};
+use cw_storage_plus::{Bound, U64Key};
+use std::collections::BTreeMap;
+use std::convert::TryInto;
 use std::str::from_utf8;
 
 const CONTRACT_NAME: &str = "Hope_Market_Place";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// Reply id of hook notifications; their failures are swallowed. Settlement
+/// submessages use ids from `REPLY_SEQ`, which start above it.
+const HOOK_REPLY_ID: u64 = 0;
+/// Gas a hook notification may use until the owner changes it.
+const DEFAULT_HOOK_GAS_LIMIT: u64 = 200_000;
+
+/// Longest timelock delay, 30 days. Changes to the delay wait for the current
+/// one, so a mistaken huge value could otherwise never be undone.
+const MAX_TIMELOCK_DELAY: u64 = 30 * 24 * 60 * 60;
+
+/// Listing denom of the HOPE cw20 token configured in `State.token_address`.
+const HOPE_DENOM: &str = "hope";
+
+struct Payout {
+    recipient: String,
+    amount: Uint128,
+}
+
 #[entry_point]
 pub fn instantiate(
     deps: DepsMut,
@@ -24,12 +56,38 @@ pub fn instantiate(
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    let remainder_address = match msg.remainder_address {
+        Some(address) => deps.api.addr_validate(&address)?.to_string(),
+        None => info.sender.to_string(),
+    };
+    let treasury = match msg.treasury {
+        Some(address) => deps.api.addr_validate(&address)?.to_string(),
+        None => info.sender.to_string(),
+    };
+    let max_royalty_portion = msg.max_royalty_portion.unwrap_or_else(Decimal::one);
+    validate_royalty_portion(msg.royalty_portion, max_royalty_portion)?;
+    let timelock_delay = msg.timelock_delay.unwrap_or(0);
+    validate_timelock_delay(timelock_delay)?;
+
     let state = State {
         owner:info.sender.to_string(),
         token_address:String::from("token_address"),
         nft_address :String::from("nft_address"),
         offering_id:0,
-        royalty_portion:msg.royalty_portion
+        royalty_portion:msg.royalty_portion,
+        remainder_address,
+        max_royalty_portion,
+        treasury,
+        timelock_delay,
+        change_id:0,
+        hope_discount:Decimal::zero(),
+        volume_window:0,
+        bundle_id:0,
+        swap_id:0,
+        collection_fee:None,
+        flag_id:0,
+        storefront_id:0,
+        hook_gas_limit:DEFAULT_HOOK_GAS_LIMIT,
     };
     CONFIG.save(deps.storage,&state)?;
     Ok(Response::default())
@@ -45,639 +103,5318 @@ pub fn execute(
     match msg {
     ExecuteMsg::ReceiveNft(msg) =>execute_receive_nft(deps,env,info,msg),
     ExecuteMsg::Receive(msg) =>execute_receive(deps,env,info,msg),
-    ExecuteMsg::BuyNft { offering_id } =>execute_buy_nft(deps,env,info,offering_id),
-    ExecuteMsg::SetAdminsList { members } => execute_set_members(deps,env,info,members),
-    ExecuteMsg::ChangeRoyaltyPortion { royalty_portion } => execute_change_royalty(deps,env,info,royalty_portion),
+    ExecuteMsg::BuyNft { offering_id, recipient, msg, referrer } =>execute_buy_nft(deps,env,info,offering_id,recipient,msg,referrer),
+    ExecuteMsg::BuyMany { offering_ids, max_total, skip_unavailable } =>execute_buy_many(deps,env,info,offering_ids,max_total,skip_unavailable),
+    ExecuteMsg::SetAdminsList { members } => execute_config_change(deps,env,info,ConfigChange::Members { members }),
+    ExecuteMsg::AddMember { address, portion } => execute_config_change(deps,env,info,ConfigChange::AddMember { address, portion }),
+    ExecuteMsg::RemoveMember { address } => execute_config_change(deps,env,info,ConfigChange::RemoveMember { address }),
+    ExecuteMsg::UpdateMemberPortion { address, portion } => execute_config_change(deps,env,info,ConfigChange::UpdateMemberPortion { address, portion }),
+    ExecuteMsg::ChangeRoyaltyPortion { royalty_portion } => execute_config_change(deps,env,info,ConfigChange::RoyaltyPortion { royalty_portion }),
     ExecuteMsg::WithdrawNft { offering_id } => execute_withdraw(deps,env,info,offering_id),
-    ExecuteMsg::SetTokenAddress {address} => execute_token_address(deps,env,info,address),
-    ExecuteMsg::SetNftAddress { address } =>execute_nft_address(deps,env,info,address),
+    ExecuteMsg::ListWithApproval { nft_address, token_id, list_price, storefront } =>
+        execute_list_with_approval(deps,env,info,nft_address,token_id,list_price,storefront),
+    ExecuteMsg::ListManyWithApproval { nft_address, tokens, list_price, storefront } =>
+        execute_list_many_with_approval(deps,env,info,nft_address,tokens,list_price,storefront),
+    ExecuteMsg::CreateBundle { items, list_price, reserved_for } => execute_create_bundle(deps,env,info,items,list_price,reserved_for),
+    ExecuteMsg::CancelBundle { bundle_id } => execute_cancel_bundle(deps,env,info,bundle_id),
+    ExecuteMsg::ProposeSwap(msg) => execute_propose_swap_native(deps,env,info,msg),
+    ExecuteMsg::CancelSwap { swap_id } => execute_cancel_swap(deps,env,info,swap_id),
+    ExecuteMsg::RegisterCollection { address } => execute_config_change(deps,env,info,ConfigChange::RegisterCollection { address }),
+    ExecuteMsg::DeregisterCollection { address } => execute_config_change(deps,env,info,ConfigChange::DeregisterCollection { address }),
+    ExecuteMsg::SubmitCollection(msg) => execute_submit_collection_native(deps,env,info,msg),
+    ExecuteMsg::ReviewCollection { address, approve, verified } => execute_review_collection(deps,env,info,address,approve,verified),
+    ExecuteMsg::SetCollectionVerified { address, verified } => execute_update_collection(deps,env,info,address,|collection| collection.verified = verified),
+    ExecuteMsg::SetCollectionTrading { address, trading_enabled } => execute_update_collection(deps,env,info,address,|collection| collection.trading_enabled = trading_enabled),
+    ExecuteMsg::SetCurators { curators } => execute_config_change(deps,env,info,ConfigChange::Curators { curators }),
+    ExecuteMsg::SetCollectionFee { fee } => execute_config_change(deps,env,info,ConfigChange::CollectionFee { fee }),
+    ExecuteMsg::SetReferrer { address, share } => execute_config_change(deps,env,info,ConfigChange::Referrer { address, share }),
+    ExecuteMsg::CreateStorefront { owner, name, royalty_portion, recipients, allowed_collections } =>
+        execute_create_storefront(deps,env,info,owner,name,royalty_portion,recipients,allowed_collections),
+    ExecuteMsg::UpdateStorefront { storefront_id, royalty_portion, recipients, allowed_collections } =>
+        execute_update_storefront(deps,env,info,storefront_id,royalty_portion,recipients,allowed_collections),
+    ExecuteMsg::AddHook { address } => execute_config_change(deps,env,info,ConfigChange::AddHook { address }),
+    ExecuteMsg::RemoveHook { address } => execute_config_change(deps,env,info,ConfigChange::RemoveHook { address }),
+    ExecuteMsg::RescueNft { nft_address, token_id, recipient } =>
+        execute_rescue_nft(deps,env,info,nft_address,token_id,recipient),
+    ExecuteMsg::RescueCoins { denom, amount, recipient } => execute_rescue_coins(deps,env,info,denom,amount,recipient),
+    ExecuteMsg::RescueCw20 { token_address, amount, recipient } =>
+        execute_rescue_cw20(deps,env,info,token_address,amount,recipient),
+    ExecuteMsg::SetPullProceeds { pull } => execute_set_pull_proceeds(deps,env,info,pull),
+    ExecuteMsg::WithdrawProceeds { asset } => execute_withdraw_proceeds(deps,env,info,asset),
+    ExecuteMsg::ClaimBalance { denom } => execute_claim_balance(deps,env,info,denom),
+    ExecuteMsg::SetModerators { moderators } => execute_config_change(deps,env,info,ConfigChange::Moderators { moderators }),
+    ExecuteMsg::FlagToken { nft_address, token_id, flagged, reason } =>
+        execute_flag(deps,env,info,FlagTarget::Token { nft_address, token_id },flagged,reason),
+    ExecuteMsg::FlagAddress { address, flagged, reason } =>
+        execute_flag(deps,env,info,FlagTarget::Address { address },flagged,reason),
+    ExecuteMsg::SetTokenAddress {address} => execute_config_change(deps,env,info,ConfigChange::TokenAddress { address }),
+    ExecuteMsg::SetNftAddress { address } =>execute_config_change(deps,env,info,ConfigChange::NftAddress { address }),
     ExecuteMsg::ChangeOwner { address } =>execute_change_owner(deps,env,info,address),
+    ExecuteMsg::SetRemainderAddress { address } =>execute_config_change(deps,env,info,ConfigChange::RemainderAddress { address }),
+    ExecuteMsg::SetTreasury { address } =>execute_config_change(deps,env,info,ConfigChange::Treasury { address }),
+    ExecuteMsg::SetMaxRoyaltyPortion { max_royalty_portion } =>execute_config_change(deps,env,info,ConfigChange::MaxRoyaltyPortion { max_royalty_portion }),
+    ExecuteMsg::SetFeeTiers { denom, tiers } =>execute_config_change(deps,env,info,ConfigChange::FeeTiers { denom, tiers }),
+    ExecuteMsg::SetHopeDiscount { hope_discount } =>execute_config_change(deps,env,info,ConfigChange::HopeDiscount { hope_discount }),
+    ExecuteMsg::SetVolumeWindow { window } =>execute_config_change(deps,env,info,ConfigChange::VolumeWindow { window }),
+    ExecuteMsg::SetHookGasLimit { gas_limit } =>execute_config_change(deps,env,info,ConfigChange::HookGasLimit { gas_limit }),
+    ExecuteMsg::SetDenom { denom, symbol, decimals, min_price } => execute_config_change(deps,env,info,ConfigChange::SetDenom {
+        denom,
+        info: DenomInfo { symbol, decimals, min_price },
+    }),
+    ExecuteMsg::RemoveDenom { denom } => execute_config_change(deps,env,info,ConfigChange::RemoveDenom { denom }),
+    ExecuteMsg::SetTimelockDelay { delay } =>execute_config_change(deps,env,info,ConfigChange::TimelockDelay { delay }),
+    ExecuteMsg::ApplyPendingChange { change_id } =>execute_apply_pending_change(deps,env,info,change_id),
+    ExecuteMsg::CancelPendingChange { change_id } =>execute_cancel_pending_change(deps,env,info,change_id),
     }
 }
 
 
 fn execute_receive_nft(
      deps: DepsMut,
-    _env:Env,
+    env:Env,
     info: MessageInfo,
     rcv_msg: Cw721ReceiveMsg,
 )-> Result<Response, ContractError> {
     
-    let mut state = CONFIG.load(deps.storage)?;
+    let state = CONFIG.load(deps.storage)?;
     
-    if info.sender.to_string()!=state.nft_address{
+    if !collection_accepted(deps.storage, &state, info.sender.as_ref())? {
         return Err(ContractError::WrongNFTContractError { });
     }
+    let received = NftItem {
+        nft_address: info.sender.to_string(),
+        token_id: rcv_msg.token_id.clone(),
+    };
+    ensure_not_flagged(deps.storage, &[&rcv_msg.sender], std::slice::from_ref(&received))?;
+    // the hook is only as trustworthy as the collection's own record
+    if nft_owner(deps.as_ref(), &received.nft_address, &received.token_id)
+        .map_or(true, |owner| owner.owner != env.contract.address.as_ref()) {
+        return Err(ContractError::NotInCustody { token_id: received.token_id });
+    }
 
-    let msg:SellNft = from_binary(&rcv_msg.msg)?;
-    
-    state.offering_id = state.offering_id + 1;
-    CONFIG.save(deps.storage, &state)?;
+    match parse_cw721_hook(&rcv_msg.msg)? {
+        Cw721HookMsg::SellNft(msg) => execute_sell_nft(deps, env, state, info.sender.to_string(), rcv_msg, msg),
+        Cw721HookMsg::DepositBundle { bundle_id } => execute_deposit_bundle(deps, env, info.sender.to_string(), rcv_msg, bundle_id),
+        Cw721HookMsg::DepositSwap { swap_id } => execute_deposit_swap(deps, env, info.sender.to_string(), rcv_msg, swap_id),
+        Cw721HookMsg::AcceptSwap { swap_id } => execute_accept_swap(deps, env, info.sender.to_string(), rcv_msg, swap_id),
+    }
+}
+
+fn parse_cw721_hook(msg: &Binary) -> StdResult<Cw721HookMsg> {
+    // plain `SellNft` payloads predate the hook enum
+    if let Ok(sell) = from_binary::<SellNft>(msg) {
+        return Ok(Cw721HookMsg::SellNft(sell));
+    }
+    from_binary(msg)
+}
+
+/// Fails unless every item's collection is still accepted for trading.
+fn ensure_tradable(storage: &dyn Storage, state: &State, items: &[NftItem]) -> Result<(), ContractError> {
+    for item in items {
+        if !collection_accepted(storage, state, &item.nft_address)? {
+            return Err(ContractError::WrongNFTContractError {});
+        }
+    }
+    Ok(())
+}
+
+/// The default collection is always accepted, other collections have to be
+/// approved with trading enabled.
+fn collection_accepted(storage: &dyn Storage, state: &State, nft_address: &str) -> StdResult<bool> {
+    if nft_address == state.nft_address {
+        return Ok(true);
+    }
+    Ok(COLLECTIONS
+        .may_load(storage, nft_address)?
+        .map_or(false, |collection| collection.status == CollectionStatus::Approved && collection.trading_enabled))
+}
+
+fn execute_sell_nft(
+    mut deps: DepsMut,
+    env:Env,
+    mut state: State,
+    nft_address: String,
+    rcv_msg: Cw721ReceiveMsg,
+    msg: SellNft,
+)-> Result<Response, ContractError> {
+    let reserved_for = validate_reserved_for(deps.as_ref(), msg.reserved_for)?;
+    let alternative_prices = msg.alternative_prices.unwrap_or_default();
+    validate_list_price(deps.storage, &msg.list_price)?;
+    for (i, price) in alternative_prices.iter().enumerate() {
+        validate_list_price(deps.storage, price)?;
+        if price.denom == msg.list_price.denom
+            || alternative_prices[..i].iter().any(|other| other.denom == price.denom) {
+            return Err(ContractError::DuplicatePrice { denom: price.denom.clone() });
+        }
+    }
+
+    let (storefront, storefront_portion) =
+        storefront_terms(deps.storage, &state, msg.storefront, &nft_address, state.royalty_portion)?;
 
     let off = Offering {
         token_id: rcv_msg.token_id,
         seller: deps.api.addr_validate(&rcv_msg.sender)?.to_string(),
         list_price: msg.list_price.clone(),
+        royalty_portion: state.royalty_portion,
+        custody: Custody::Escrow,
+        reserved_for,
+        nft_address,
+        bundle: vec![],
+        alternative_prices,
+        storefront,
+        storefront_portion,
+        pull_proceeds: msg.pull_proceeds.unwrap_or_default(),
     };
+    let mut hooks = ensure_not_listed(deps.branch(), &env, &off.items())?;
 
-    OFFERINGS.save(deps.storage, &state.offering_id.to_string(), &off)?;
+    state.offering_id += 1;
+    CONFIG.save(deps.storage, &state)?;
+    save_offering(deps.storage, &state.offering_id.to_string(), &off)?;
     let price_string = format!("{} ", msg.list_price.amount);
+    hooks.extend(listed_hook_msgs(deps.storage, &state.offering_id.to_string(), &off)?);
 
     Ok(Response::new()
         .add_attribute("price_string", price_string)
+        .add_submessages(hooks)
     )
 }
 
-fn execute_receive(
+fn validate_reserved_for(deps: Deps, reserved_for: Option<Vec<String>>) -> StdResult<Vec<String>> {
+    reserved_for
+        .unwrap_or_default()
+        .iter()
+        .map(|address| Ok(deps.api.addr_validate(address)?.to_string()))
+        .collect()
+}
+
+/// Listing prices have to be positive and, unless in HOPE, use a registered
+/// native denom at or above its minimum.
+fn validate_list_price(storage: &dyn Storage, price: &Asset) -> Result<(), ContractError> {
+    validate_denom(&price.denom)?;
+    let min_price = if price.denom == HOPE_DENOM {
+        Uint128::zero()
+    } else {
+        match DENOMS.may_load(storage, &price.denom)? {
+            Some(info) => info.min_price,
+            None => return Err(ContractError::UnsupportedDenom { denom: price.denom.clone() }),
+        }
+    };
+    if price.amount.is_zero() || price.amount < min_price {
+        return Err(ContractError::PriceTooLow {
+            denom: price.denom.clone(),
+            min_price: min_price.max(Uint128::new(1)),
+        });
+    }
+    Ok(())
+}
+
+/// Denoms follow the bank module's format: a letter followed by 2 to 127
+/// letters, digits or `/:._-`.
+fn validate_denom(denom: &str) -> Result<(), ContractError> {
+    let mut chars = denom.chars();
+    let well_formed = (3..=128).contains(&denom.len())
+        && chars.next().map_or(false, |first| first.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || "/:._-".contains(c));
+    if !well_formed {
+        return Err(ContractError::InvalidDenom { denom: denom.to_string() });
+    }
+    Ok(())
+}
+
+fn execute_create_bundle(
     deps: DepsMut,
     _env:Env,
     info: MessageInfo,
-    rcv_msg: Cw20ReceiveMsg,
+    items: Vec<NftItem>,
+    list_price: Asset,
+    reserved_for: Option<Vec<String>>,
 )-> Result<Response, ContractError> {
-    let state = CONFIG.load(deps.storage)?;
-
-    if info.sender.to_string() != state.token_address{
-        return Err(ContractError::WrongTokenContractError  { })
+    let mut state = CONFIG.load(deps.storage)?;
+    if items.len() < 2 {
+        return Err(ContractError::NoData {});
+    }
+    validate_list_price(deps.storage, &list_price)?;
+    ensure_not_flagged(deps.storage, &[info.sender.as_ref()], &items)?;
+    for (i, item) in items.iter().enumerate() {
+        if !collection_accepted(deps.storage, &state, &item.nft_address)? {
+            return Err(ContractError::WrongNFTContractError {});
+        }
+        if items[..i].contains(item) {
+            return Err(ContractError::DuplicateToken { token_id: item.token_id.clone() });
+        }
     }
 
-    let msg:BuyNft = from_binary(&rcv_msg.msg)?;
-    let off = OFFERINGS.load(deps.storage, &msg.offering_id)?;
+    state.bundle_id += 1;
+    CONFIG.save(deps.storage, &state)?;
+    let bundle = Bundle {
+        seller: info.sender.to_string(),
+        items: items
+            .into_iter()
+            .map(|item| BundleItem { item, deposited: false })
+            .collect(),
+        list_price,
+        reserved_for: validate_reserved_for(deps.as_ref(), reserved_for)?,
+    };
+    BUNDLES.save(deps.storage, &state.bundle_id.to_string(), &bundle)?;
 
-    
-    if off.list_price.denom != "hope".to_string(){
-        return Err(ContractError::NotEnoughFunds  { })
+    Ok(Response::new()
+        .add_attribute("action", "create_bundle")
+        .add_attribute("bundle_id", state.bundle_id.to_string()))
+}
+
+/// Escrows one piece of a bundle. The bundle is listed once its last piece
+/// arrives.
+fn execute_deposit_bundle(
+    mut deps: DepsMut,
+    env:Env,
+    nft_address: String,
+    rcv_msg: Cw721ReceiveMsg,
+    bundle_id: String,
+)-> Result<Response, ContractError> {
+    let mut bundle = BUNDLES.load(deps.storage, &bundle_id)?;
+    if rcv_msg.sender != bundle.seller {
+        return Err(ContractError::Unauthorized {});
     }
 
-    if off.list_price.amount > rcv_msg.amount{
-        return Err(ContractError::NotEnoughFunds  { })
+    let deposited = NftItem {
+        nft_address,
+        token_id: rcv_msg.token_id,
+    };
+    match bundle.items.iter_mut().find(|piece| piece.item == deposited && !piece.deposited) {
+        Some(piece) => piece.deposited = true,
+        None => return Err(ContractError::NotInBundle { token_id: deposited.token_id }),
     }
 
-    OFFERINGS.remove( deps.storage, &msg.offering_id);
-    let members = MEMBERS.load(deps.storage)?;
-    
-    let mut messages:Vec<CosmosMsg> = vec![];
-    for user in members{
-        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: state.token_address.to_string(),
-                funds: vec![],
-                msg: to_binary(&Cw20ExecuteMsg::Transfer { 
-                    recipient: user.address.clone(), 
-                    amount: rcv_msg.amount*state.royalty_portion*user.portion })?,
-        }))
+    if bundle.items.iter().any(|piece| !piece.deposited) {
+        BUNDLES.save(deps.storage, &bundle_id, &bundle)?;
+        return Ok(Response::new()
+            .add_attribute("action", "deposit_bundle")
+            .add_attribute("bundle_id", bundle_id));
     }
 
+    let items: Vec<NftItem> = bundle.items.into_iter().map(|piece| piece.item).collect();
+    let mut hooks = ensure_not_listed(deps.branch(), &env, &items)?;
+    BUNDLES.remove(deps.storage, &bundle_id);
+    let mut state = CONFIG.load(deps.storage)?;
+    state.offering_id += 1;
+    CONFIG.save(deps.storage, &state)?;
+
+    let off = Offering {
+        token_id: items[0].token_id.clone(),
+        seller: bundle.seller,
+        list_price: bundle.list_price,
+        royalty_portion: state.royalty_portion,
+        custody: Custody::Escrow,
+        reserved_for: bundle.reserved_for,
+        nft_address: items[0].nft_address.clone(),
+        bundle: items,
+        alternative_prices: vec![],
+        storefront: None,
+        storefront_portion: Decimal::zero(),
+        pull_proceeds: false,
+    };
+    save_offering(deps.storage, &state.offering_id.to_string(), &off)?;
+    hooks.extend(listed_hook_msgs(deps.storage, &state.offering_id.to_string(), &off)?);
+
     Ok(Response::new()
-        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: state.nft_address.to_string(),
-                funds: vec![],
-                msg: to_binary(&Cw721ExecuteMsg::TransferNft {
-                    recipient: deps.api.addr_validate(&rcv_msg.sender)?.to_string(),
-                    token_id: off.token_id.clone(),
-            })?,
-        }))
-        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: state.token_address.to_string(),
-                funds: vec![],
-                msg: to_binary(&Cw20ExecuteMsg::Transfer { 
-                    recipient: off.seller, 
-                    amount: rcv_msg.amount*(Decimal::one()-state.royalty_portion) })?,
-        }))
-        .add_messages(messages)
-)
+        .add_attribute("action", "list_bundle")
+        .add_attribute("bundle_id", bundle_id)
+        .add_attribute("offering_id", state.offering_id.to_string())
+        .add_submessages(hooks))
 }
 
-fn execute_buy_nft(
+/// Drops an unfinished bundle and returns the pieces deposited so far.
+fn execute_cancel_bundle(
     deps: DepsMut,
     _env:Env,
     info: MessageInfo,
-    offering_id: String,
-) -> Result<Response, ContractError> {
-     let state = CONFIG.load(deps.storage)?;
-
-   
-    let off = OFFERINGS.load(deps.storage, &offering_id)?;
+    bundle_id: String,
+)-> Result<Response, ContractError> {
+    let bundle = BUNDLES.load(deps.storage, &bundle_id)?;
+    if info.sender != bundle.seller {
+        return Err(ContractError::Unauthorized {});
+    }
+    BUNDLES.remove(deps.storage, &bundle_id);
 
-    let amount= info
-        .funds
+    let messages = bundle
+        .items
         .iter()
-        .find(|c| c.denom == off.list_price.denom)
-        .map(|c| Uint128::from(c.amount))
-        .unwrap_or_else(Uint128::zero);
+        .filter(|piece| piece.deposited)
+        .map(|piece| nft_delivery_msg(deps.as_ref(), &piece.item.nft_address, &piece.item.token_id, &bundle.seller, None))
+        .collect::<StdResult<Vec<CosmosMsg>>>()?;
+    Ok(Response::new()
+        .add_attribute("action", "cancel_bundle")
+        .add_attribute("bundle_id", bundle_id)
+        .add_messages(messages))
+}
 
-    if off.list_price.amount>amount{
-        return Err(ContractError::NotEnoughFunds {  })
-    }
 
-    OFFERINGS.remove( deps.storage, &offering_id);
-    
-    let members = MEMBERS.load(deps.storage)?;
-    
-    let mut messages:Vec<CosmosMsg> = vec![];
-    for user in members{
-        messages.push(CosmosMsg::Bank(BankMsg::Send {
-                to_address: user.address,
-                amount:vec![Coin{
-                    denom:off.clone().list_price.denom,
-                    amount:amount*state.royalty_portion*user.portion
-                }]
-        }))
+fn execute_propose_swap(
+    deps: DepsMut,
+    env:Env,
+    proposer: String,
+    sweetener: Option<Asset>,
+    msg: ProposeSwap,
+)-> Result<Response, ContractError> {
+    let mut state = CONFIG.load(deps.storage)?;
+    if msg.offered.is_empty() {
+        return Err(ContractError::NoData {});
+    }
+    ensure_not_flagged(deps.storage, &[&proposer], &msg.offered)?;
+    ensure_not_flagged(deps.storage, &[], std::slice::from_ref(&msg.wanted))?;
+    if !collection_accepted(deps.storage, &state, &msg.wanted.nft_address)? {
+        return Err(ContractError::WrongNFTContractError {});
+    }
+    for (i, item) in msg.offered.iter().enumerate() {
+        if !collection_accepted(deps.storage, &state, &item.nft_address)? {
+            return Err(ContractError::WrongNFTContractError {});
+        }
+        if msg.offered[..i].contains(item) || *item == msg.wanted {
+            return Err(ContractError::DuplicateToken { token_id: item.token_id.clone() });
+        }
+    }
+    let swap = Swap {
+        proposer,
+        offered: msg
+            .offered
+            .into_iter()
+            .map(|item| BundleItem { item, deposited: false })
+            .collect(),
+        sweetener,
+        wanted: msg.wanted,
+        end_height: msg.end_height,
+        end_time: msg.end_time,
+    };
+    if swap.is_expired(&env) {
+        return Err(ContractError::Expired { end_height: swap.end_height, end_time: swap.end_time });
     }
 
+    state.swap_id += 1;
+    CONFIG.save(deps.storage, &state)?;
+    SWAPS.save(deps.storage, &state.swap_id.to_string(), &swap)?;
+
     Ok(Response::new()
-        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: state.nft_address.to_string(),
-                funds: vec![],
-                msg: to_binary(&Cw721ExecuteMsg::TransferNft {
-                    recipient: info.sender.to_string(),
-                    token_id: off.token_id.clone(),
-            })?,
-        }))
-        .add_message(CosmosMsg::Bank(BankMsg::Send {
-                to_address: off.seller,
-                amount:vec![Coin{
-                    denom:off.list_price.denom,
-                    amount:amount*(Decimal::one()-state.royalty_portion)
-                }]
-        }))
-        .add_messages(messages)
-)
+        .add_attribute("action", "propose_swap")
+        .add_attribute("swap_id", state.swap_id.to_string()))
 }
 
-fn execute_withdraw(
+/// HOPE amounts are paid out as cw20 transfers, so a native coin that happens
+/// to be called "hope" must not stand in for them.
+fn ensure_no_native_hope(funds: &[Coin]) -> Result<(), ContractError> {
+    if funds.iter().any(|coin| coin.denom == HOPE_DENOM) {
+        return Err(ContractError::NativeHope {});
+    }
+    Ok(())
+}
+
+fn execute_propose_swap_native(
     deps: DepsMut,
-    _env:Env,
+    env:Env,
     info: MessageInfo,
-    offering_id: String,
-) -> Result<Response, ContractError> {
-    let off = OFFERINGS.load(deps.storage,&offering_id)?;
+    msg: ProposeSwap,
+)-> Result<Response, ContractError> {
+    ensure_no_native_hope(&info.funds)?;
+    let sweetener = match info.funds.as_slice() {
+        [] => None,
+        [coin] if !coin.amount.is_zero() => Some(Asset {
+            denom: coin.denom.clone(),
+            amount: coin.amount,
+        }),
+        _ => return Err(ContractError::InvalidFunds {}),
+    };
+    execute_propose_swap(deps, env, info.sender.to_string(), sweetener, msg)
+}
+
+/// Escrows one of the tokens the proposer offers in a swap.
+fn execute_deposit_swap(
+    deps: DepsMut,
+    env:Env,
+    nft_address: String,
+    rcv_msg: Cw721ReceiveMsg,
+    swap_id: String,
+)-> Result<Response, ContractError> {
+    let mut swap = SWAPS.load(deps.storage, &swap_id)?;
+    if rcv_msg.sender != swap.proposer {
+        return Err(ContractError::Unauthorized {});
+    }
+    if swap.is_expired(&env) {
+        return Err(ContractError::Expired { end_height: swap.end_height, end_time: swap.end_time });
+    }
+
+    let deposited = NftItem {
+        nft_address,
+        token_id: rcv_msg.token_id,
+    };
+    match swap.offered.iter_mut().find(|piece| piece.item == deposited && !piece.deposited) {
+        Some(piece) => piece.deposited = true,
+        None => return Err(ContractError::NotInBundle { token_id: deposited.token_id }),
+    }
+    SWAPS.save(deps.storage, &swap_id, &swap)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "deposit_swap")
+        .add_attribute("swap_id", swap_id))
+}
+
+/// Takes the wanted token from the counterparty and completes the swap.
+fn execute_accept_swap(
+    deps: DepsMut,
+    env:Env,
+    nft_address: String,
+    rcv_msg: Cw721ReceiveMsg,
+    swap_id: String,
+)-> Result<Response, ContractError> {
     let state = CONFIG.load(deps.storage)?;
+    let swap = SWAPS.load(deps.storage, &swap_id)?;
+    if swap.is_expired(&env) {
+        return Err(ContractError::Expired { end_height: swap.end_height, end_time: swap.end_time });
+    }
+    if nft_address != swap.wanted.nft_address || rcv_msg.token_id != swap.wanted.token_id {
+        return Err(ContractError::WrongNFTContractError {});
+    }
+    if swap.offered.iter().any(|piece| !piece.deposited) {
+        return Err(ContractError::SwapNotFunded {});
+    }
+    let offered: Vec<NftItem> = swap.offered.iter().map(|piece| piece.item.clone()).collect();
+    ensure_not_flagged(deps.storage, &[&swap.proposer], &offered)?;
+    ensure_tradable(deps.storage, &state, &offered)?;
+    SWAPS.remove(deps.storage, &swap_id);
 
-    if off.seller == info.sender.to_string(){
-        OFFERINGS.remove(deps.storage,&offering_id);
-        Ok(Response::new()
-            .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: state.nft_address.to_string(),
-                funds: vec![],
-                msg: to_binary(&Cw721ExecuteMsg::TransferNft {
-                    recipient: deps.api.addr_validate(&off.seller)?.to_string(),
-                    token_id: off.token_id.clone(),
-            })?,
-        }))
-    )
+    let counterparty = deps.api.addr_validate(&rcv_msg.sender)?.to_string();
+    let mut messages = vec![nft_delivery_msg(
+        deps.as_ref(),
+        &swap.wanted.nft_address,
+        &swap.wanted.token_id,
+        &swap.proposer,
+        None,
+    )?];
+    for piece in swap.offered.iter() {
+        messages.push(nft_delivery_msg(deps.as_ref(), &piece.item.nft_address, &piece.item.token_id, &counterparty, None)?);
     }
-    else {
-        return Err(ContractError::Unauthorized {});
+    if let Some(sweetener) = swap.sweetener {
+        let payout = Payout { recipient: counterparty.clone(), amount: sweetener.amount };
+        messages.extend(payout_msgs(&state, &sweetener.denom, vec![payout])?);
     }
-    
+    let hooks = hook_msgs(deps.storage, MarketplaceEvent::SwapAccepted {
+        swap_id: swap_id.clone(),
+        proposer: swap.proposer,
+        counterparty: counterparty.clone(),
+        offered,
+        wanted: swap.wanted,
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "accept_swap")
+        .add_attribute("swap_id", swap_id)
+        .add_attribute("counterparty", counterparty)
+        .add_messages(messages)
+        .add_submessages(hooks))
 }
 
-fn execute_set_members(
+/// Refunds the proposer's side. Anyone may clear an expired proposal, only the
+/// proposer can cancel one that is still open.
+fn execute_cancel_swap(
     deps: DepsMut,
-    _env:Env,
+    env:Env,
     info: MessageInfo,
-    members: Vec<UserInfo>,
-)->Result<Response,ContractError>{
-
+    swap_id: String,
+)-> Result<Response, ContractError> {
     let state = CONFIG.load(deps.storage)?;
-
-    if info.sender.to_string() != state.owner{
+    let swap = SWAPS.load(deps.storage, &swap_id)?;
+    if info.sender != swap.proposer && !swap.is_expired(&env) {
         return Err(ContractError::Unauthorized {});
     }
-    
-    let mut sum_portion = Decimal::zero();
+    SWAPS.remove(deps.storage, &swap_id);
 
-    for item in members.clone() {
-        sum_portion = sum_portion + item.portion;
-        deps.api.addr_validate(&item.address)?;
+    let mut messages = swap
+        .offered
+        .iter()
+        .filter(|piece| piece.deposited)
+        .map(|piece| nft_delivery_msg(deps.as_ref(), &piece.item.nft_address, &piece.item.token_id, &swap.proposer, None))
+        .collect::<StdResult<Vec<CosmosMsg>>>()?;
+    if let Some(sweetener) = swap.sweetener {
+        let payout = Payout { recipient: swap.proposer.clone(), amount: sweetener.amount };
+        messages.extend(payout_msgs(&state, &sweetener.denom, vec![payout])?);
     }
 
-    if sum_portion != Decimal::one(){
-        return Err(ContractError::WrongPortionError { })
+    Ok(Response::new()
+        .add_attribute("action", "cancel_swap")
+        .add_attribute("swap_id", swap_id)
+        .add_messages(messages))
+}
+
+
+fn execute_submit_collection(
+    deps: DepsMut,
+    _env:Env,
+    creator: String,
+    paid: Option<Asset>,
+    msg: SubmitCollection,
+)-> Result<Response, ContractError> {
+    let state = CONFIG.load(deps.storage)?;
+    let address = deps.api.addr_validate(&msg.address)?.to_string();
+    if address == state.nft_address {
+        return Err(ContractError::CollectionExists { address });
+    }
+    if let Some(existing) = COLLECTIONS.may_load(deps.storage, &address)? {
+        if existing.status != CollectionStatus::Rejected {
+            return Err(ContractError::CollectionExists { address });
+        }
+    }
+    if paid != state.collection_fee {
+        return Err(ContractError::InvalidFunds {});
+    }
+    // only whoever controls the collection may put it up for review
+    let response: MinterResponse = deps.querier.query_wasm_smart(&address, &CollectionQueryMsg::Minter {})?;
+    if response.minter != creator {
+        return Err(ContractError::NotCollectionMinter { address });
     }
 
-    MEMBERS.save(deps.storage, &members)?;
-    Ok(Response::default())
+    let collection = CollectionInfo {
+        name: msg.name,
+        description: msg.description,
+        creator,
+        verified: false,
+        trading_enabled: false,
+        status: CollectionStatus::Pending,
+        fee_paid: paid,
+    };
+    COLLECTIONS.save(deps.storage, &address, &collection)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "submit_collection")
+        .add_attribute("address", address))
 }
 
-fn execute_change_royalty(
-     deps: DepsMut,
-    _env:Env,
+fn execute_submit_collection_native(
+    deps: DepsMut,
+    env:Env,
     info: MessageInfo,
-    royalty_potion: Decimal,
-)->Result<Response,ContractError>{
-    let mut state = CONFIG.load(deps.storage)?;    
-    if info.sender.to_string() != state.owner{
-        return Err(ContractError::Unauthorized {});
-    }
+    msg: SubmitCollection,
+)-> Result<Response, ContractError> {
+    ensure_no_native_hope(&info.funds)?;
+    let paid = match info.funds.as_slice() {
+        [] => None,
+        [coin] => Some(Asset {
+            denom: coin.denom.clone(),
+            amount: coin.amount,
+        }),
+        _ => return Err(ContractError::InvalidFunds {}),
+    };
+    execute_submit_collection(deps, env, info.sender.to_string(), paid, msg)
+}
 
-    state.royalty_portion = royalty_potion;
-    CONFIG.save(deps.storage, &state)?;
-    Ok(Response::default())
+fn is_curator(storage: &dyn Storage, state: &State, address: &str) -> StdResult<bool> {
+    if address == state.owner {
+        return Ok(true);
+    }
+    Ok(CURATORS
+        .may_load(storage)?
+        .unwrap_or_default()
+        .iter()
+        .any(|curator| curator == address))
 }
 
-fn execute_token_address(
+fn execute_review_collection(
     deps: DepsMut,
     _env:Env,
     info: MessageInfo,
     address: String,
-) -> Result<Response, ContractError> {
-    let mut state = CONFIG.load(deps.storage)?;
-    deps.api.addr_validate(&address)?;
-    
-    state.token_address = address;
-
-    if state.owner != info.sender.to_string() {
+    approve: bool,
+    verified: bool,
+)-> Result<Response, ContractError> {
+    let state = CONFIG.load(deps.storage)?;
+    if !is_curator(deps.storage, &state, info.sender.as_ref())? {
         return Err(ContractError::Unauthorized {});
     }
+    let mut collection = COLLECTIONS.load(deps.storage, &address)?;
+    if collection.status != CollectionStatus::Pending {
+        return Err(ContractError::NotPending { address });
+    }
 
-    CONFIG.save(deps.storage, &state)?;
-    Ok(Response::default())
+    // the fee goes to the treasury on approval and back to the creator otherwise
+    let fee_recipient = if approve {
+        collection.status = CollectionStatus::Approved;
+        collection.trading_enabled = true;
+        collection.verified = verified;
+        state.treasury.clone()
+    } else {
+        collection.status = CollectionStatus::Rejected;
+        collection.creator.clone()
+    };
+    let messages = match collection.fee_paid.take() {
+        Some(fee) => payout_msgs(&state, &fee.denom, vec![Payout { recipient: fee_recipient, amount: fee.amount }])?,
+        None => vec![],
+    };
+    COLLECTIONS.save(deps.storage, &address, &collection)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "review_collection")
+        .add_attribute("address", address)
+        .add_attribute("approved", approve.to_string())
+        .add_messages(messages))
 }
 
-fn execute_nft_address(
+fn execute_update_collection(
     deps: DepsMut,
     _env:Env,
     info: MessageInfo,
     address: String,
-) -> Result<Response, ContractError> {
-    let mut state = CONFIG.load(deps.storage)?;
-    deps.api.addr_validate(&address)?;
-    state.nft_address = address;
-    
-    if state.owner != info.sender.to_string() {
+    update: impl FnOnce(&mut CollectionInfo),
+)-> Result<Response, ContractError> {
+    let state = CONFIG.load(deps.storage)?;
+    if !is_curator(deps.storage, &state, info.sender.as_ref())? {
         return Err(ContractError::Unauthorized {});
     }
+    let mut collection = COLLECTIONS.load(deps.storage, &address)?;
+    update(&mut collection);
+    COLLECTIONS.save(deps.storage, &address, &collection)?;
 
-    CONFIG.save(deps.storage, &state)?;
-    Ok(Response::default())
+    Ok(Response::new()
+        .add_attribute("action", "update_collection")
+        .add_attribute("address", address)
+        .add_attribute("verified", collection.verified.to_string())
+        .add_attribute("trading_enabled", collection.trading_enabled.to_string()))
 }
 
 
-fn execute_change_owner(
+fn is_moderator(storage: &dyn Storage, state: &State, address: &str) -> StdResult<bool> {
+    if address == state.owner {
+        return Ok(true);
+    }
+    Ok(MODERATORS
+        .may_load(storage)?
+        .unwrap_or_default()
+        .iter()
+        .any(|moderator| moderator == address))
+}
+
+/// Fails on the first blocked address or flagged token.
+fn ensure_not_flagged(storage: &dyn Storage, addresses: &[&str], items: &[NftItem]) -> Result<(), ContractError> {
+    for address in addresses {
+        if let Some(reason) = BLOCKED_ADDRESSES.may_load(storage, address)? {
+            return Err(ContractError::AddressBlocked { address: address.to_string(), reason });
+        }
+    }
+    for item in items {
+        if let Some(reason) = FLAGGED_TOKENS.may_load(storage, (&item.nft_address, &item.token_id))? {
+            return Err(ContractError::TokenFlagged { token_id: item.token_id.clone(), reason });
+        }
+    }
+    Ok(())
+}
+
+fn is_flagged(storage: &dyn Storage, addresses: &[&str], items: &[NftItem]) -> StdResult<bool> {
+    match ensure_not_flagged(storage, addresses, items) {
+        Ok(()) => Ok(false),
+        Err(ContractError::Std(err)) => Err(err),
+        Err(_) => Ok(true),
+    }
+}
+
+fn execute_flag(
     deps: DepsMut,
-    _env:Env,
+    env:Env,
     info: MessageInfo,
-    address: String,
-) -> Result<Response, ContractError> {
+    target: FlagTarget,
+    flagged: bool,
+    reason: String,
+)-> Result<Response, ContractError> {
     let mut state = CONFIG.load(deps.storage)?;
-
-    if state.owner != info.sender.to_string() {
+    if !is_moderator(deps.storage, &state, info.sender.as_ref())? {
         return Err(ContractError::Unauthorized {});
     }
-    deps.api.addr_validate(&address)?;
-    state.owner = address;
-    CONFIG.save(deps.storage,&state)?;
-    Ok(Response::default())
-}
 
+    match &target {
+        FlagTarget::Token { nft_address, token_id } if flagged =>
+            FLAGGED_TOKENS.save(deps.storage, (nft_address, token_id), &reason)?,
+        FlagTarget::Token { nft_address, token_id } =>
+            FLAGGED_TOKENS.remove(deps.storage, (nft_address, token_id)),
+        FlagTarget::Address { address } if flagged =>
+            BLOCKED_ADDRESSES.save(deps.storage, address, &reason)?,
+        FlagTarget::Address { address } =>
+            BLOCKED_ADDRESSES.remove(deps.storage, address),
+    }
 
+    state.flag_id += 1;
+    CONFIG.save(deps.storage, &state)?;
+    let record = FlagRecord {
+        target,
+        flagged,
+        reason,
+        moderator: info.sender.to_string(),
+        height: env.block.height,
+    };
+    FLAG_LOG.save(deps.storage, U64Key::new(state.flag_id), &record)?;
 
-#[entry_point]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::GetStateInfo {} => to_binary(&query_state_info(deps)?),
-        QueryMsg::GetOfferings {} => to_binary(&query_get_offerings(deps)?),
-        QueryMsg::GetMembers {} => to_binary(&query_get_members(deps)?)
-    }
+    Ok(Response::new()
+        .add_attribute("action", "flag")
+        .add_attribute("flag_id", state.flag_id.to_string())
+        .add_attribute("flagged", flagged.to_string())
+        .add_attribute("reason", record.reason))
 }
 
-pub fn query_state_info(deps:Deps) -> StdResult<State>{
-    let state =  CONFIG.load(deps.storage)?;
-    Ok(state)
+
+fn validate_storefront(deps: Deps, state: &State, storefront: &mut Storefront) -> Result<(), ContractError> {
+    validate_royalty_portion(storefront.royalty_portion, state.max_royalty_portion)?;
+    storefront.recipients = validate_members(deps, std::mem::take(&mut storefront.recipients))?;
+    Ok(())
 }
 
-pub fn query_get_members(deps:Deps) -> StdResult<Vec<UserInfo>>{
-    let members = MEMBERS.load(deps.storage)?;
-    Ok(members)
+#[allow(clippy::too_many_arguments)]
+fn execute_create_storefront(
+    deps: DepsMut,
+    _env:Env,
+    info: MessageInfo,
+    owner: String,
+    name: String,
+    royalty_portion: Decimal,
+    recipients: Vec<UserInfo>,
+    allowed_collections: Vec<String>,
+)-> Result<Response, ContractError> {
+    let mut state = CONFIG.load(deps.storage)?;
+    if info.sender != state.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    let mut storefront = Storefront {
+        owner: deps.api.addr_validate(&owner)?.to_string(),
+        name,
+        royalty_portion,
+        recipients,
+        allowed_collections,
+    };
+    validate_storefront(deps.as_ref(), &state, &mut storefront)?;
+
+    state.storefront_id += 1;
+    CONFIG.save(deps.storage, &state)?;
+    STOREFRONTS.save(deps.storage, &state.storefront_id.to_string(), &storefront)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "create_storefront")
+        .add_attribute("storefront_id", state.storefront_id.to_string()))
 }
 
-pub fn query_get_offerings(deps:Deps) -> StdResult<OfferingsResponse>{
-    let res: StdResult<Vec<QueryOfferingsResult>> = OFFERINGS
-        .range(deps.storage, None, None, Order::Ascending)
-        .map(|kv_item| parse_offering(deps, kv_item))
-        .collect();
-    Ok(OfferingsResponse {
-        offerings: res?, // Placeholder
-    })
+fn execute_update_storefront(
+    deps: DepsMut,
+    _env:Env,
+    info: MessageInfo,
+    storefront_id: String,
+    royalty_portion: Decimal,
+    recipients: Vec<UserInfo>,
+    allowed_collections: Vec<String>,
+)-> Result<Response, ContractError> {
+    let state = CONFIG.load(deps.storage)?;
+    let mut storefront = STOREFRONTS.load(deps.storage, &storefront_id)?;
+    if info.sender != storefront.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    storefront.royalty_portion = royalty_portion;
+    storefront.recipients = recipients;
+    storefront.allowed_collections = allowed_collections;
+    validate_storefront(deps.as_ref(), &state, &mut storefront)?;
+    STOREFRONTS.save(deps.storage, &storefront_id, &storefront)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_storefront")
+        .add_attribute("storefront_id", storefront_id))
 }
 
-fn parse_offering(
-    deps:Deps,
-    item: StdResult<Pair<Offering>>,
-) -> StdResult<QueryOfferingsResult> {
-    item.and_then(|(k, offering)| {
-        let id = from_utf8(&k)?;
-        Ok(QueryOfferingsResult {
-            id: id.to_string(),
-            token_id: offering.token_id,
-            list_price: offering.list_price,
-            seller: deps.api.addr_validate(&offering.seller)?.to_string(),
-        })
-    })
+/// Storefront tag and fee for a new listing of `nft_address`. The storefront
+/// fee and the platform fee together may not exceed the price.
+fn storefront_terms(
+    storage: &dyn Storage,
+    state: &State,
+    storefront_id: Option<String>,
+    nft_address: &str,
+    royalty_portion: Decimal,
+) -> Result<(Option<String>, Decimal), ContractError> {
+    let storefront_id = match storefront_id {
+        Some(storefront_id) => storefront_id,
+        None => return Ok((None, Decimal::zero())),
+    };
+    let storefront = STOREFRONTS.load(storage, &storefront_id)?;
+    let allowed = if storefront.allowed_collections.is_empty() {
+        collection_accepted(storage, state, nft_address)?
+    } else {
+        storefront.allowed_collections.iter().any(|address| address == nft_address)
+    };
+    if !allowed {
+        return Err(ContractError::CollectionNotInStorefront { storefront_id });
+    }
+    if royalty_portion + storefront.royalty_portion > Decimal::one() {
+        return Err(ContractError::WrongPortionError {});
+    }
+    Ok((Some(storefront_id), storefront.royalty_portion))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-    use cosmwasm_std::{ CosmosMsg, Coin};
+fn execute_list_with_approval(
+    deps: DepsMut,
+    env:Env,
+    info: MessageInfo,
+    nft_address: Option<String>,
+    token_id: String,
+    list_price: Asset,
+    storefront: Option<String>,
+)-> Result<Response, ContractError> {
+    let mut state = CONFIG.load(deps.storage)?;
+    let nft_address = nft_address.unwrap_or_else(|| state.nft_address.clone());
+    let (offering_id, hooks) = create_approval_offering(
+        deps,
+        &env,
+        &mut state,
+        info.sender.as_ref(),
+        nft_address,
+        token_id,
+        list_price.clone(),
+        storefront,
+    )?;
 
-    #[test]
-    fn testing() {
-        //Instantiate
-        let mut deps = mock_dependencies(&[]);
-        let instantiate_msg = InstantiateMsg {
-            royalty_portion:Decimal::from_ratio(2 as u128, 100 as u128)
+    Ok(Response::new()
+        .add_attribute("action", "list_with_approval")
+        .add_attribute("offering_id", offering_id)
+        .add_attribute("price_string", format!("{} ", list_price.amount))
+        .add_submessages(hooks)
+    )
+}
+
+fn execute_list_many_with_approval(
+    mut deps: DepsMut,
+    env:Env,
+    info: MessageInfo,
+    nft_address: Option<String>,
+    tokens: Vec<ApprovalListing>,
+    list_price: Option<Asset>,
+    storefront: Option<String>,
+)-> Result<Response, ContractError> {
+    let mut state = CONFIG.load(deps.storage)?;
+    let nft_address = nft_address.unwrap_or_else(|| state.nft_address.clone());
+    if tokens.is_empty() {
+        return Err(ContractError::NoData {});
+    }
+
+    let mut listings: Vec<(String, Asset)> = vec![];
+    for token in tokens {
+        if listings.iter().any(|(token_id, _)| *token_id == token.token_id) {
+            return Err(ContractError::DuplicateToken { token_id: token.token_id });
+        }
+        match token.list_price.or_else(|| list_price.clone()) {
+            Some(price) => listings.push((token.token_id, price)),
+            None => return Err(ContractError::MissingPrice { token_id: token.token_id }),
+        }
+    }
+
+    let mut events: Vec<Event> = vec![];
+    let mut hooks: Vec<SubMsg> = vec![];
+    for (token_id, price) in listings {
+        let (offering_id, listed_hooks) = create_approval_offering(
+            deps.branch(),
+            &env,
+            &mut state,
+            info.sender.as_ref(),
+            nft_address.clone(),
+            token_id.clone(),
+            price.clone(),
+            storefront.clone(),
+        )?;
+        hooks.extend(listed_hooks);
+        events.push(Event::new("list_with_approval")
+            .add_attribute("offering_id", offering_id)
+            .add_attribute("token_id", token_id)
+            .add_attribute("price_string", format!("{} ", price.amount)));
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "list_many_with_approval")
+        .add_events(events)
+        .add_submessages(hooks))
+}
+
+/// Stores an approval listing after checking the seller's approval and
+/// returns its offering id with the hook notifications.
+#[allow(clippy::too_many_arguments)]
+fn create_approval_offering(
+    mut deps: DepsMut,
+    env: &Env,
+    state: &mut State,
+    seller: &str,
+    nft_address: String,
+    token_id: String,
+    list_price: Asset,
+    storefront: Option<String>,
+) -> Result<(String, Vec<SubMsg>), ContractError> {
+    if !collection_accepted(deps.storage, state, &nft_address)? {
+        return Err(ContractError::WrongNFTContractError {});
+    }
+    validate_list_price(deps.storage, &list_price)?;
+    let item = NftItem {
+        nft_address: nft_address.clone(),
+        token_id: token_id.clone(),
+    };
+    ensure_not_flagged(deps.storage, &[seller], std::slice::from_ref(&item))?;
+    let mut hooks = ensure_not_listed(deps.branch(), env, &[item])?;
+    if !approval_valid(deps.as_ref(), env, &nft_address, &token_id, seller)? {
+        return Err(ContractError::ApprovalMissing {});
+    }
+    let (storefront, storefront_portion) =
+        storefront_terms(deps.storage, state, storefront, &nft_address, state.royalty_portion)?;
+
+    state.offering_id += 1;
+    CONFIG.save(deps.storage, state)?;
+
+    let off = Offering {
+        token_id,
+        seller: seller.to_string(),
+        list_price,
+        royalty_portion: state.royalty_portion,
+        custody: Custody::Approval,
+        reserved_for: vec![],
+        nft_address,
+        bundle: vec![],
+        alternative_prices: vec![],
+        storefront,
+        storefront_portion,
+        pull_proceeds: false,
+    };
+    let offering_id = state.offering_id.to_string();
+    save_offering(deps.storage, &offering_id, &off)?;
+    hooks.extend(listed_hook_msgs(deps.storage, &offering_id, &off)?);
+    Ok((offering_id, hooks))
+}
+
+/// Whether `seller` still owns the token and the marketplace is approved to
+/// transfer it.
+fn approval_valid(
+    deps: Deps,
+    env: &Env,
+    nft_address: &str,
+    token_id: &str,
+    seller: &str,
+) -> StdResult<bool> {
+    // a burned token can't be bought any more either
+    let owner = match nft_owner(deps, nft_address, token_id) {
+        Ok(owner) => owner,
+        Err(_) => return Ok(false),
+    };
+    Ok(owner.owner == seller
+        && owner
+            .approvals
+            .iter()
+            .any(|approval| approval.spender == env.contract.address.as_ref()))
+}
+
+fn nft_owner(deps: Deps, nft_address: &str, token_id: &str) -> StdResult<OwnerOfResponse> {
+    deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: nft_address.to_string(),
+        msg: to_binary(&Cw721QueryMsg::OwnerOf {
+            token_id: token_id.to_string(),
+            include_expired: Some(false),
+        })?,
+    }))
+}
+
+/// Fails if any of `items` is already part of an open offering. Approval
+/// listings that went stale, because the seller moved the token or revoked
+/// the approval, are dropped instead of blocking the token's new owner; the
+/// hook notifications of their cancellation are returned.
+fn ensure_not_listed(deps: DepsMut, env: &Env, items: &[NftItem]) -> Result<Vec<SubMsg>, ContractError> {
+    let mut stale: Vec<(String, Offering)> = vec![];
+    for item in items {
+        let offering_id = match LISTED_TOKENS.may_load(deps.storage, (&item.nft_address, &item.token_id))? {
+            Some(offering_id) => offering_id,
+            None => continue,
         };
-        let info = mock_info("creator", &[]);
-        let res = instantiate(deps.as_mut(), mock_env(), info, instantiate_msg).unwrap();
-        assert_eq!(0, res.messages.len());
-        let state = query_state_info(deps.as_ref()).unwrap();
-        assert_eq!(state.owner,"creator".to_string());
-        assert_eq!(state.royalty_portion,Decimal::from_ratio(2 as u128, 100 as u128));
+        let off = OFFERINGS.load(deps.storage, &offering_id)?;
+        if off.custody == Custody::Approval
+            && !approval_valid(deps.as_ref(), env, &off.nft_address, &off.token_id, &off.seller)? {
+            stale.push((offering_id, off));
+            continue;
+        }
+        return Err(ContractError::AlreadyListed {
+            token_id: item.token_id.clone(),
+            offering_id,
+        });
+    }
+    let mut hooks: Vec<SubMsg> = vec![];
+    for (offering_id, off) in stale {
+        remove_offering(deps.storage, &offering_id, &off);
+        hooks.extend(cancelled_hook_msgs(deps.storage, &offering_id, &off)?);
+    }
+    Ok(hooks)
+}
 
-        //Change Owner
+/// Stores an offering and indexes its tokens as listed.
+fn save_offering(storage: &mut dyn Storage, offering_id: &str, off: &Offering) -> StdResult<()> {
+    for item in off.items() {
+        LISTED_TOKENS.save(storage, (&item.nft_address, &item.token_id), &offering_id.to_string())?;
+    }
+    OFFERINGS.save(storage, offering_id, off)
+}
 
-        let info = mock_info("creator", &[]);
-        let msg = ExecuteMsg::ChangeOwner { address:"owner".to_string()};
-        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-        let state = query_state_info(deps.as_ref()).unwrap();
-        assert_eq!(state.owner,"owner".to_string());
+fn remove_offering(storage: &mut dyn Storage, offering_id: &str, off: &Offering) {
+    for item in off.items() {
+        LISTED_TOKENS.remove(storage, (&item.nft_address, &item.token_id));
+    }
+    OFFERINGS.remove(storage, offering_id);
+}
 
-        //Set Members
+/// Drops an approval listing whose token moved or lost its approval, handing
+/// the buyer's payment back instead of failing the whole transaction.
+fn remove_stale_listing(
+    deps: DepsMut,
+    offering_id: &str,
+    off: &Offering,
+    refund: Vec<CosmosMsg>,
+) -> Result<Response, ContractError> {
+    remove_offering(deps.storage, offering_id, off);
+    let hooks = cancelled_hook_msgs(deps.storage, offering_id, off)?;
+    Ok(Response::new()
+        .add_attribute("action", "remove_stale_listing")
+        .add_attribute("offering_id", offering_id)
+        .add_messages(refund)
+        .add_submessages(hooks))
+}
 
-        let info = mock_info("owner", &[]);
-        let msg = ExecuteMsg::SetAdminsList { members: vec![UserInfo{
-            address:"admin1".to_string(),
-            portion:Decimal::from_ratio(3 as u128, 10 as u128)
-        },UserInfo{
-            address:"admin2".to_string(),
-            portion:Decimal::from_ratio(7 as u128, 10 as u128)
-        }] }; 
-        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+fn execute_receive(
+    deps: DepsMut,
+    env:Env,
+    info: MessageInfo,
+    rcv_msg: Cw20ReceiveMsg,
+)-> Result<Response, ContractError> {
+    let state = CONFIG.load(deps.storage)?;
 
-        let members = query_get_members(deps.as_ref()).unwrap();
-        assert_eq!(members, vec![UserInfo{
-            address:"admin1".to_string(),
-            portion:Decimal::from_ratio(3 as u128, 10 as u128)
-        },UserInfo{
-            address:"admin2".to_string(),
-            portion:Decimal::from_ratio(7 as u128, 10 as u128)
+    if info.sender != state.token_address{
+        return Err(ContractError::WrongTokenContractError  { })
+    }
+    ensure_not_flagged(deps.storage, &[&rcv_msg.sender], &[])?;
+
+    match parse_cw20_hook(&rcv_msg.msg)? {
+        Cw20HookMsg::BuyNft(msg) => execute_receive_buy_nft(deps, env, state, rcv_msg.sender, rcv_msg.amount, msg),
+        Cw20HookMsg::BuyMany(msg) => buy_many(
+            deps,
+            env,
+            &state,
+            &rcv_msg.sender,
+            HOPE_DENOM,
+            rcv_msg.amount,
+            msg.offering_ids,
+            msg.max_total,
+            msg.skip_unavailable,
+        ),
+        Cw20HookMsg::ProposeSwap(msg) => {
+            let sweetener = Asset {
+                denom: HOPE_DENOM.to_string(),
+                amount: rcv_msg.amount,
+            };
+            execute_propose_swap(deps, env, rcv_msg.sender, Some(sweetener), msg)
+        }
+        Cw20HookMsg::SubmitCollection(msg) => {
+            let fee = Asset {
+                denom: HOPE_DENOM.to_string(),
+                amount: rcv_msg.amount,
+            };
+            execute_submit_collection(deps, env, rcv_msg.sender, Some(fee), msg)
+        }
+    }
+}
+
+fn parse_cw20_hook(msg: &Binary) -> StdResult<Cw20HookMsg> {
+    // plain `BuyNft` payloads predate the hook enum
+    if let Ok(buy) = from_binary::<BuyNft>(msg) {
+        return Ok(Cw20HookMsg::BuyNft(buy));
+    }
+    from_binary(msg)
+}
+
+fn execute_receive_buy_nft(
+    deps: DepsMut,
+    env:Env,
+    state: State,
+    sender: String,
+    amount: Uint128,
+    msg: BuyNft,
+)-> Result<Response, ContractError> {
+    let off = OFFERINGS.load(deps.storage, &msg.offering_id)?;
+    ensure_not_flagged(deps.storage, &[msg.recipient.as_deref().unwrap_or(&sender), &off.seller], &off.items())?;
+
+    
+    let price = match off.price_in(HOPE_DENOM) {
+        Some(price) => price,
+        None => return Err(ContractError::NotEnoughFunds  { }),
+    };
+
+    if price.amount > amount{
+        return Err(ContractError::NotEnoughFunds  { })
+    }
+
+    if !off.reserved_for_buyer(&sender) {
+        return Err(ContractError::ReservedListing {});
+    }
+
+    if off.custody == Custody::Approval
+        && !approval_valid(deps.as_ref(), &env, &off.nft_address, &off.token_id, &off.seller)? {
+        let refund = payout_msgs(&state, HOPE_DENOM, vec![Payout {
+            recipient: sender,
+            amount,
+        }])?;
+        return remove_stale_listing(deps, &msg.offering_id, &off, refund);
+    }
+
+    let recipient = msg.recipient.unwrap_or_else(|| sender.clone());
+    let messages = buy_offering(
+        deps,
+        &env,
+        &state,
+        &msg.offering_id,
+        &off,
+        &sender,
+        &recipient,
+        msg.msg,
+        msg.referrer.as_deref(),
+        HOPE_DENOM,
+        amount,
+    )?;
+    Ok(Response::new().add_submessages(messages))
+}
+
+fn execute_buy_nft(
+    deps: DepsMut,
+    env:Env,
+    info: MessageInfo,
+    offering_id: String,
+    recipient: Option<String>,
+    msg: Option<Binary>,
+    referrer: Option<String>,
+) -> Result<Response, ContractError> {
+     let state = CONFIG.load(deps.storage)?;
+    ensure_no_native_hope(&info.funds)?;
+   
+    let off = OFFERINGS.load(deps.storage, &offering_id)?;
+    let buyer = info.sender.as_str();
+    ensure_not_flagged(deps.storage, &[buyer, recipient.as_deref().unwrap_or(buyer), &off.seller], &off.items())?;
+
+    // a second coin would be kept without being owed to anyone
+    if info.funds.len() > 1 {
+        return Err(ContractError::InvalidFunds {});
+    }
+    let (price, amount) = info
+        .funds
+        .iter()
+        .find_map(|c| off.price_in(&c.denom).map(|price| (price.clone(), c.amount)))
+        .unwrap_or_else(|| (off.list_price.clone(), Uint128::zero()));
+
+    if price.amount>amount{
+        return Err(ContractError::NotEnoughFunds {  })
+    }
+
+    if !off.reserved_for_buyer(info.sender.as_ref()) {
+        return Err(ContractError::ReservedListing {});
+    }
+
+    if off.custody == Custody::Approval
+        && !approval_valid(deps.as_ref(), &env, &off.nft_address, &off.token_id, &off.seller)? {
+        let refund = vec![CosmosMsg::Bank(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: info.funds,
+        })];
+        return remove_stale_listing(deps, &offering_id, &off, refund);
+    }
+
+    let recipient = recipient.unwrap_or_else(|| info.sender.to_string());
+    let messages = buy_offering(
+        deps,
+        &env,
+        &state,
+        &offering_id,
+        &off,
+        info.sender.as_ref(),
+        &recipient,
+        msg,
+        referrer.as_deref(),
+        &price.denom,
+        amount,
+    )?;
+    Ok(Response::new().add_submessages(messages))
+}
+
+fn execute_buy_many(
+    deps: DepsMut,
+    env:Env,
+    info: MessageInfo,
+    offering_ids: Vec<String>,
+    max_total: Uint128,
+    skip_unavailable: bool,
+) -> Result<Response, ContractError> {
+    let state = CONFIG.load(deps.storage)?;
+    ensure_no_native_hope(&info.funds)?;
+    if info.funds.len() != 1 {
+        return Err(ContractError::InvalidFunds {});
+    }
+    let funds = &info.funds[0];
+
+    buy_many(
+        deps,
+        env,
+        &state,
+        info.sender.as_ref(),
+        &funds.denom,
+        funds.amount,
+        offering_ids,
+        max_total,
+        skip_unavailable,
+    )
+}
+
+/// Buys every available offering in `offering_ids` at its list price out of
+/// `sent` and refunds what is left. Unavailable offerings either fail the
+/// whole purchase or are skipped.
+#[allow(clippy::too_many_arguments)]
+fn buy_many(
+    mut deps: DepsMut,
+    env: Env,
+    state: &State,
+    buyer: &str,
+    denom: &str,
+    sent: Uint128,
+    offering_ids: Vec<String>,
+    max_total: Uint128,
+    skip_unavailable: bool,
+) -> Result<Response, ContractError> {
+    ensure_not_flagged(deps.storage, &[buyer], &[])?;
+    let mut cart: Vec<(String, Offering)> = vec![];
+    let mut skipped: Vec<String> = vec![];
+    for offering_id in offering_ids {
+        let available = match OFFERINGS.may_load(deps.storage, &offering_id)? {
+            Some(off) if cart.iter().all(|(id, _)| *id != offering_id)
+                && offering_available(deps.as_ref(), &env, state, &off, buyer, denom)? => Some(off),
+            _ => None,
+        };
+        match available {
+            Some(off) => cart.push((offering_id, off)),
+            None if skip_unavailable => skipped.push(offering_id),
+            None => return Err(ContractError::OfferingUnavailable { offering_id }),
+        }
+    }
+    if cart.is_empty() {
+        return Err(ContractError::NoData {});
+    }
+
+    let price_of = |off: &Offering| off.price_in(denom).map_or_else(Uint128::zero, |price| price.amount);
+    let total: Uint128 = cart.iter().map(|(_, off)| price_of(off)).sum();
+    if total > max_total {
+        return Err(ContractError::MaxTotalExceeded { total, max_total });
+    }
+    if total > sent {
+        return Err(ContractError::NotEnoughFunds {});
+    }
+
+    let mut messages: Vec<SubMsg> = vec![];
+    for (offering_id, off) in &cart {
+        messages.extend(buy_offering(
+            deps.branch(),
+            &env,
+            state,
+            offering_id,
+            off,
+            buyer,
+            buyer,
+            None,
+            None,
+            denom,
+            price_of(off),
+        )?);
+    }
+    let refund = payout_msgs(state, denom, vec![Payout {
+        recipient: buyer.to_string(),
+        amount: sent - total,
+    }])?;
+    messages.extend(refund.into_iter().map(SubMsg::new));
+
+    Ok(Response::new()
+        .add_attribute("action", "buy_many")
+        .add_attribute("bought", cart.len().to_string())
+        .add_attribute("skipped", skipped.join(","))
+        .add_attribute("total", total.to_string())
+        .add_submessages(messages))
+}
+
+/// Whether `buyer` can buy an offering with `denom` right now.
+fn offering_available(
+    deps: Deps,
+    env: &Env,
+    state: &State,
+    off: &Offering,
+    buyer: &str,
+    denom: &str,
+) -> StdResult<bool> {
+    if off.price_in(denom).is_none() || !off.reserved_for_buyer(buyer) {
+        return Ok(false);
+    }
+    for item in off.items() {
+        if !collection_accepted(deps.storage, state, &item.nft_address)? {
+            return Ok(false);
+        }
+    }
+    if is_flagged(deps.storage, &[&off.seller], &off.items())? {
+        return Ok(false);
+    }
+    if off.custody == Custody::Approval {
+        return approval_valid(deps, env, &off.nft_address, &off.token_id, &off.seller);
+    }
+    Ok(true)
+}
+
+/// Removes a sold offering and returns the token delivery followed by the
+/// payouts of `amount` and the hook notifications. The sale is recorded when
+/// the deliveries are confirmed; failed payouts become claimable.
+#[allow(clippy::too_many_arguments)]
+fn buy_offering(
+    deps: DepsMut,
+    env: &Env,
+    state: &State,
+    offering_id: &str,
+    off: &Offering,
+    buyer: &str,
+    recipient: &str,
+    msg: Option<Binary>,
+    referrer: Option<&str>,
+    denom: &str,
+    amount: Uint128,
+) -> Result<Vec<SubMsg>, ContractError> {
+    // listings outlive their collection's trading status
+    ensure_tradable(deps.storage, state, &off.items())?;
+    remove_offering(deps.storage, offering_id, off);
+    let (mut payouts, settlement) = settle(deps.as_ref(), env, state, off, buyer, referrer, denom, amount)?;
+    SETTLEMENTS.save(deps.storage, offering_id, &settlement)?;
+    if off.pull_proceeds || PULL_PROCEEDS.has(deps.storage, &off.seller) {
+        // the seller's payout comes first and is kept for them instead
+        let proceeds = std::mem::take(&mut payouts[0].amount);
+        credit_proceeds(deps.storage, &off.seller, denom, proceeds)?;
+    }
+
+    let mut sub_msgs: Vec<SubMsg> = vec![];
+    for item in off.items() {
+        let delivery = nft_delivery_msg(deps.as_ref(), &item.nft_address, &item.token_id, recipient, msg.clone())?;
+        let id = next_reply_id(deps.storage)?;
+        PENDING_REPLIES.save(deps.storage, U64Key::new(id), &PendingReply::Delivery {
+            offering_id: offering_id.to_string(),
+        })?;
+        sub_msgs.push(SubMsg::reply_on_success(delivery, id));
+    }
+    for payout in payouts.into_iter().filter(|payout| !payout.amount.is_zero()) {
+        let asset = Asset {
+            denom: denom.to_string(),
+            amount: payout.amount,
+        };
+        let recipient = payout.recipient.clone();
+        for transfer in payout_msgs(state, denom, vec![payout])? {
+            let id = next_reply_id(deps.storage)?;
+            PENDING_REPLIES.save(deps.storage, U64Key::new(id), &PendingReply::Payout {
+                recipient: recipient.clone(),
+                asset: asset.clone(),
+            })?;
+            sub_msgs.push(SubMsg::reply_always(transfer, id));
+        }
+    }
+
+    sub_msgs.extend(hook_msgs(deps.storage, MarketplaceEvent::Sold {
+        offering_id: offering_id.to_string(),
+        seller: off.seller.clone(),
+        buyer: buyer.to_string(),
+        items: off.items(),
+        price: Asset {
+            denom: denom.to_string(),
+            amount,
+        },
+    })?);
+    Ok(sub_msgs)
+}
+
+/// Delivers a bought token. With a `msg` the token is sent with `SendNft` so
+/// the receiving contract's hook runs, otherwise it is transferred.
+fn nft_delivery_msg(
+    deps: Deps,
+    nft_address: &str,
+    token_id: &str,
+    recipient: &str,
+    msg: Option<Binary>,
+) -> StdResult<CosmosMsg> {
+    let recipient = deps.api.addr_validate(recipient)?.to_string();
+    let msg = match msg {
+        Some(msg) => Cw721ExecuteMsg::SendNft {
+            contract: recipient,
+            token_id: token_id.to_string(),
+            msg,
+        },
+        None => Cw721ExecuteMsg::TransferNft {
+            recipient,
+            token_id: token_id.to_string(),
+        },
+    };
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: nft_address.to_string(),
+        funds: vec![],
+        msg: to_binary(&msg)?,
+    }))
+}
+
+/// Splits `amount` of `denom` between the seller and the fee recipients and
+/// returns the payouts with the settlement to record once delivered.
+#[allow(clippy::too_many_arguments)]
+fn settle(
+    deps: Deps,
+    env: &Env,
+    state: &State,
+    off: &Offering,
+    buyer: &str,
+    referrer: Option<&str>,
+    denom: &str,
+    amount: Uint128,
+) -> Result<(Vec<Payout>, Settlement), ContractError> {
+    let royalty_portion = effective_royalty_portion(
+        deps,
+        env,
+        state,
+        off.royalty_portion,
+        buyer,
+        denom,
+    )?;
+
+    // unregistered referrers are ignored
+    let referral = match referrer {
+        Some(referrer) => REFERRERS
+            .may_load(deps.storage, referrer)?
+            .map(|share| (referrer, share)),
+        None => None,
+    };
+
+    let members = fee_members(deps, state)?;
+    let mut payouts = compute_payouts(
+        amount,
+        royalty_portion,
+        referral,
+        &members,
+        &off.seller,
+        &state.remainder_address,
+    );
+    let settlement = Settlement {
+        buyer: buyer.to_string(),
+        seller: off.seller.clone(),
+        price: Asset {
+            denom: denom.to_string(),
+            amount,
+        },
+        referrer: referral.map(|(referrer, _)| referrer.to_string()),
+        // the referrer's payout directly follows the seller's
+        referrer_earned: referral.map_or_else(Uint128::zero, |_| payouts[1].amount),
+        deliveries_left: off.items().len() as u64,
+    };
+    if let Some(storefront_id) = &off.storefront {
+        // the storefront fee comes out of the seller's proceeds
+        let storefront = STOREFRONTS.load(deps.storage, storefront_id)?;
+        let mut storefront_payouts = compute_payouts(
+            amount,
+            off.storefront_portion,
+            None,
+            &storefront.recipients,
+            &off.seller,
+            &storefront.owner,
+        );
+        payouts[0].amount -= amount - storefront_payouts[0].amount;
+        payouts.extend(storefront_payouts.drain(1..));
+    }
+    Ok((payouts, settlement))
+}
+
+/// Fee rate `trader` pays in `denom`: the best volume tier they reached,
+/// capped by `royalty_portion`, with the HOPE discount applied on top.
+fn effective_royalty_portion(
+    deps: Deps,
+    env: &Env,
+    state: &State,
+    royalty_portion: Decimal,
+    trader: &str,
+    denom: &str,
+) -> StdResult<Decimal> {
+    let volume = rolling_volume(deps.storage, env, state, trader, denom)?;
+    let tiers = FEE_TIERS.may_load(deps.storage, denom)?.unwrap_or_default();
+    let mut rate = royalty_portion;
+    if let Some(tier) = tiers.iter().rev().find(|tier| tier.min_volume <= volume) {
+        if tier.royalty_portion < rate {
+            rate = tier.royalty_portion;
+        }
+    }
+    if denom == HOPE_DENOM {
+        rate = decimal_mul(rate, Decimal::one() - state.hope_discount);
+    }
+    Ok(rate)
+}
+
+fn decimal_mul(a: Decimal, b: Decimal) -> Decimal {
+    Decimal::from_ratio(Uint128::new(a.numerator()) * b, a.denominator())
+}
+
+fn volume_window_index(env: &Env, state: &State) -> u64 {
+    if state.volume_window == 0 {
+        return 0;
+    }
+    env.block.time.seconds() / state.volume_window
+}
+
+/// Volume traded in the current and the previous window. Without a window
+/// all volume counts.
+fn rolling_volume(
+    storage: &dyn Storage,
+    env: &Env,
+    state: &State,
+    trader: &str,
+    denom: &str,
+) -> StdResult<Uint128> {
+    let index = volume_window_index(env, state);
+    let volume = match TRADER_VOLUMES.may_load(storage, (trader, denom))? {
+        Some(volume) => volume,
+        None => return Ok(Uint128::zero()),
+    };
+    if volume.window == index {
+        Ok(volume.current + volume.previous)
+    } else if volume.window + 1 == index {
+        Ok(volume.current)
+    } else {
+        Ok(Uint128::zero())
+    }
+}
+
+fn record_volume(
+    storage: &mut dyn Storage,
+    env: &Env,
+    state: &State,
+    trader: &str,
+    denom: &str,
+    amount: Uint128,
+) -> StdResult<()> {
+    let index = volume_window_index(env, state);
+    let mut volume = TRADER_VOLUMES.may_load(storage, (trader, denom))?.unwrap_or_default();
+    if volume.window + 1 == index {
+        volume.previous = volume.current;
+        volume.current = Uint128::zero();
+    } else if volume.window != index {
+        volume.previous = Uint128::zero();
+        volume.current = Uint128::zero();
+    }
+    volume.window = index;
+    volume.current += amount;
+    TRADER_VOLUMES.save(storage, (trader, denom), &volume)
+}
+
+/// Members sharing the fee of a sale. Without a configured member list the
+/// treasury receives the whole fee.
+fn fee_members(deps: Deps, state: &State) -> StdResult<Vec<UserInfo>> {
+    let members = MEMBERS.may_load(deps.storage)?.unwrap_or_default();
+    if members.is_empty() {
+        return Ok(vec![UserInfo {
+            address: state.treasury.clone(),
+            portion: Decimal::one(),
         }]);
-     
-        //Chage Portion
+    }
+    Ok(members)
+}
 
-        let info = mock_info("owner", &[]);
-        let msg = ExecuteMsg::ChangeRoyaltyPortion { royalty_portion: Decimal::from_ratio(3 as u128, 100 as u128) };
-        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-        let state = query_state_info(deps.as_ref()).unwrap();
-        assert_eq!(state.royalty_portion,Decimal::from_ratio(3 as u128, 100 as u128));
-        
-        //Change Token Contract Address
+/// Splits `amount` into the seller proceeds, one fee share per member and the
+/// rounding remainder. Member portions are weights, so every member receives
+/// `portion / sum of portions` of the fee. The returned payouts always add up
+/// to exactly `amount`.
+fn compute_payouts(
+    amount: Uint128,
+    royalty_portion: Decimal,
+    referral: Option<(&str, Decimal)>,
+    members: &[UserInfo],
+    seller: &str,
+    remainder_address: &str,
+) -> Vec<Payout> {
+    let mut fee = amount * royalty_portion;
+    let mut payouts = vec![Payout {
+        recipient: seller.to_string(),
+        amount: amount - fee,
+    }];
+    if let Some((referrer, share)) = referral {
+        let cut = fee * share;
+        fee -= cut;
+        payouts.push(Payout {
+            recipient: referrer.to_string(),
+            amount: cut,
+        });
+    }
+
+    // validated splits never overflow, see `total_weight`
+    let total_weight: u128 = members.iter().map(|user| user.portion.numerator()).sum();
+    let mut distributed = Uint128::zero();
+    for user in members {
+        let share = fee.multiply_ratio(user.portion.numerator(), total_weight);
+        distributed += share;
+        payouts.push(Payout {
+            recipient: user.address.clone(),
+            amount: share,
+        });
+    }
+
+    payouts.push(Payout {
+        recipient: remainder_address.to_string(),
+        amount: fee - distributed,
+    });
+    payouts
+}
+
+/// Notifies every hook contract of `event`. Each notification runs with its
+/// own gas limit, as running out of gas without one aborts the whole trade.
+fn hook_msgs(storage: &dyn Storage, event: MarketplaceEvent) -> StdResult<Vec<SubMsg>> {
+    let gas_limit = CONFIG.load(storage)?.hook_gas_limit;
+    let msg = to_binary(&MarketplaceHookMsg::MarketplaceEvent(event))?;
+    Ok(HOOKS
+        .may_load(storage)?
+        .unwrap_or_default()
+        .into_iter()
+        .map(|contract_addr| SubMsg::reply_on_error(WasmMsg::Execute {
+            contract_addr,
+            msg: msg.clone(),
+            funds: vec![],
+        }, HOOK_REPLY_ID).with_gas_limit(gas_limit))
+        .collect())
+}
+
+fn listed_hook_msgs(storage: &dyn Storage, offering_id: &str, off: &Offering) -> StdResult<Vec<SubMsg>> {
+    hook_msgs(storage, MarketplaceEvent::Listed {
+        offering_id: offering_id.to_string(),
+        seller: off.seller.clone(),
+        items: off.items(),
+        list_price: off.list_price.clone(),
+    })
+}
+
+fn cancelled_hook_msgs(storage: &dyn Storage, offering_id: &str, off: &Offering) -> StdResult<Vec<SubMsg>> {
+    hook_msgs(storage, MarketplaceEvent::Cancelled {
+        offering_id: offering_id.to_string(),
+        seller: off.seller.clone(),
+        items: off.items(),
+    })
+}
+
+/// Turns payouts into transfers of `denom`, skipping empty ones. The HOPE
+/// denom is paid out through the cw20 token contract, anything else is sent
+/// as native coins.
+fn payout_msgs(
+    state: &State,
+    denom: &str,
+    payouts: Vec<Payout>,
+) -> StdResult<Vec<CosmosMsg>> {
+    payouts
+        .into_iter()
+        .filter(|payout| !payout.amount.is_zero())
+        .map(|payout| {
+            if denom == HOPE_DENOM {
+                Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: state.token_address.to_string(),
+                    funds: vec![],
+                    msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                        recipient: payout.recipient,
+                        amount: payout.amount,
+                    })?,
+                }))
+            } else {
+                Ok(CosmosMsg::Bank(BankMsg::Send {
+                    to_address: payout.recipient,
+                    amount: vec![Coin {
+                        denom: denom.to_string(),
+                        amount: payout.amount,
+                    }],
+                }))
+            }
+        })
+        .collect()
+}
+
+fn execute_withdraw(
+    deps: DepsMut,
+    _env:Env,
+    info: MessageInfo,
+    offering_id: String,
+) -> Result<Response, ContractError> {
+    let off = OFFERINGS.load(deps.storage,&offering_id)?;
+
+    if info.sender == off.seller {
+        remove_offering(deps.storage, &offering_id, &off);
+        let hooks = cancelled_hook_msgs(deps.storage, &offering_id, &off)?;
+        // approval listings never left the seller's wallet
+        if off.custody == Custody::Approval {
+            return Ok(Response::new()
+                .add_attribute("action", "cancel_listing")
+                .add_submessages(hooks));
+        }
+        let messages = off
+            .items()
+            .iter()
+            .map(|item| nft_delivery_msg(deps.as_ref(), &item.nft_address, &item.token_id, &off.seller, None))
+            .collect::<StdResult<Vec<CosmosMsg>>>()?;
+        Ok(Response::new().add_messages(messages).add_submessages(hooks))
+    }
+    else {
+        Err(ContractError::Unauthorized {})
+    }
+    
+}
+
+fn execute_config_change(
+    deps: DepsMut,
+    env:Env,
+    info: MessageInfo,
+    change: ConfigChange,
+)->Result<Response,ContractError>{
+    let mut state = CONFIG.load(deps.storage)?;
+    if info.sender != state.owner{
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let change = validate_config_change(deps.as_ref(), &state, change)?;
+    if state.timelock_delay == 0 {
+        return apply_config_change(deps, change);
+    }
+
+    state.change_id += 1;
+    CONFIG.save(deps.storage, &state)?;
+    let executable_at = env.block.time.seconds()
+        .checked_add(state.timelock_delay)
+        .ok_or(ContractError::TimelockTooLong { delay: state.timelock_delay, max_delay: MAX_TIMELOCK_DELAY })?;
+    PENDING_CHANGES.save(deps.storage, &state.change_id.to_string(), &PendingChange {
+        change,
+        executable_at,
+    })?;
+
+    Ok(Response::new().add_event(Event::new("config_change_queued")
+        .add_attribute("change_id", state.change_id.to_string())
+        .add_attribute("executable_at", executable_at.to_string())))
+}
+
+fn execute_apply_pending_change(
+    deps: DepsMut,
+    env:Env,
+    info: MessageInfo,
+    change_id: String,
+)->Result<Response,ContractError>{
+    let state = CONFIG.load(deps.storage)?;
+    if info.sender != state.owner{
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let pending = PENDING_CHANGES.load(deps.storage, &change_id)?;
+    if env.block.time.seconds() < pending.executable_at {
+        return Err(ContractError::ChangeNotReady { executable_at: pending.executable_at });
+    }
+    PENDING_CHANGES.remove(deps.storage, &change_id);
+
+    // the config may have moved on while the change was queued
+    let change = validate_config_change(deps.as_ref(), &state, pending.change)?;
+    Ok(apply_config_change(deps, change)?
+        .add_event(Event::new("config_change_executed").add_attribute("change_id", change_id)))
+}
+
+fn execute_cancel_pending_change(
+    deps: DepsMut,
+    _env:Env,
+    info: MessageInfo,
+    change_id: String,
+)->Result<Response,ContractError>{
+    let state = CONFIG.load(deps.storage)?;
+    if info.sender != state.owner{
+        return Err(ContractError::Unauthorized {});
+    }
+
+    PENDING_CHANGES.load(deps.storage, &change_id)?;
+    PENDING_CHANGES.remove(deps.storage, &change_id);
+    Ok(Response::new().add_event(Event::new("config_change_cancelled")
+        .add_attribute("change_id", change_id)))
+}
+
+/// Checks a config change against the current config so that mistakes are
+/// rejected when the change is submitted. Addresses are returned normalized.
+fn validate_config_change(
+    deps: Deps,
+    state: &State,
+    change: ConfigChange,
+)->Result<ConfigChange,ContractError>{
+    let change = match change {
+        ConfigChange::RoyaltyPortion { royalty_portion } => {
+            validate_royalty_portion(royalty_portion, state.max_royalty_portion)?;
+            ConfigChange::RoyaltyPortion { royalty_portion }
+        }
+        ConfigChange::MaxRoyaltyPortion { max_royalty_portion } => {
+            validate_royalty_portion(state.royalty_portion, max_royalty_portion)?;
+            ConfigChange::MaxRoyaltyPortion { max_royalty_portion }
+        }
+        ConfigChange::TokenAddress { address } => ConfigChange::TokenAddress {
+            address: deps.api.addr_validate(&address)?.to_string(),
+        },
+        ConfigChange::NftAddress { address } => ConfigChange::NftAddress {
+            address: deps.api.addr_validate(&address)?.to_string(),
+        },
+        ConfigChange::RemainderAddress { address } => ConfigChange::RemainderAddress {
+            address: deps.api.addr_validate(&address)?.to_string(),
+        },
+        ConfigChange::Treasury { address } => ConfigChange::Treasury {
+            address: deps.api.addr_validate(&address)?.to_string(),
+        },
+        ConfigChange::Members { members } => ConfigChange::Members {
+            members: validate_members(deps, members)?,
+        },
+        ConfigChange::AddMember { address, portion } => {
+            let address = deps.api.addr_validate(&address)?.to_string();
+            if portion.is_zero() {
+                return Err(ContractError::ZeroPortion {});
+            }
+            let members = MEMBERS.may_load(deps.storage)?.unwrap_or_default();
+            if members.iter().any(|user| user.address == address) {
+                return Err(ContractError::DuplicateMember { address });
+            }
+            total_weight(members.iter().map(|user| user.portion).chain(std::iter::once(portion)))?;
+            ConfigChange::AddMember { address, portion }
+        }
+        ConfigChange::RemoveMember { address } => {
+            let members = MEMBERS.may_load(deps.storage)?.unwrap_or_default();
+            if !members.iter().any(|user| user.address == address) {
+                return Err(ContractError::MemberNotFound { address });
+            }
+            ConfigChange::RemoveMember { address }
+        }
+        ConfigChange::UpdateMemberPortion { address, portion } => {
+            if portion.is_zero() {
+                return Err(ContractError::ZeroPortion {});
+            }
+            let members = MEMBERS.may_load(deps.storage)?.unwrap_or_default();
+            if !members.iter().any(|user| user.address == address) {
+                return Err(ContractError::MemberNotFound { address });
+            }
+            let others = members.iter().filter(|user| user.address != address).map(|user| user.portion);
+            total_weight(others.chain(std::iter::once(portion)))?;
+            ConfigChange::UpdateMemberPortion { address, portion }
+        }
+        ConfigChange::TimelockDelay { delay } => {
+            validate_timelock_delay(delay)?;
+            ConfigChange::TimelockDelay { delay }
+        }
+        ConfigChange::FeeTiers { denom, mut tiers } => {
+            for tier in &tiers {
+                validate_royalty_portion(tier.royalty_portion, state.max_royalty_portion)?;
+            }
+            tiers.sort_by_key(|tier| tier.min_volume);
+            if tiers.windows(2).any(|pair| pair[0].min_volume == pair[1].min_volume) {
+                return Err(ContractError::DuplicateFeeTier {});
+            }
+            ConfigChange::FeeTiers { denom, tiers }
+        }
+        ConfigChange::HopeDiscount { hope_discount } => {
+            if hope_discount > Decimal::one() {
+                return Err(ContractError::WrongPortionError {});
+            }
+            ConfigChange::HopeDiscount { hope_discount }
+        }
+        ConfigChange::VolumeWindow { window } => ConfigChange::VolumeWindow { window },
+        ConfigChange::HookGasLimit { gas_limit } => {
+            if gas_limit == 0 {
+                return Err(ContractError::ZeroGasLimit {});
+            }
+            ConfigChange::HookGasLimit { gas_limit }
+        }
+        ConfigChange::RegisterCollection { address } => ConfigChange::RegisterCollection {
+            address: deps.api.addr_validate(&address)?.to_string(),
+        },
+        ConfigChange::DeregisterCollection { address } => {
+            COLLECTIONS.load(deps.storage, &address)?;
+            ConfigChange::DeregisterCollection { address }
+        }
+        ConfigChange::SetDenom { denom, info } => {
+            // HOPE is paid through its cw20 contract, not as a native coin
+            if denom == HOPE_DENOM {
+                return Err(ContractError::UnsupportedDenom { denom });
+            }
+            validate_denom(&denom)?;
+            ConfigChange::SetDenom { denom, info }
+        }
+        ConfigChange::RemoveDenom { denom } => {
+            DENOMS.load(deps.storage, &denom)?;
+            ConfigChange::RemoveDenom { denom }
+        }
+        ConfigChange::Curators { curators } => ConfigChange::Curators {
+            curators: curators
+                .iter()
+                .map(|curator| Ok(deps.api.addr_validate(curator)?.to_string()))
+                .collect::<StdResult<Vec<String>>>()?,
+        },
+        ConfigChange::Referrer { address, share } => {
+            if share.map_or(false, |share| share > Decimal::one()) {
+                return Err(ContractError::WrongPortionError {});
+            }
+            ConfigChange::Referrer {
+                address: deps.api.addr_validate(&address)?.to_string(),
+                share,
+            }
+        }
+        ConfigChange::AddHook { address } => {
+            let address = deps.api.addr_validate(&address)?.to_string();
+            if HOOKS.may_load(deps.storage)?.unwrap_or_default().contains(&address) {
+                return Err(ContractError::DuplicateHook { address });
+            }
+            ConfigChange::AddHook { address }
+        }
+        ConfigChange::RemoveHook { address } => {
+            if !HOOKS.may_load(deps.storage)?.unwrap_or_default().contains(&address) {
+                return Err(ContractError::HookNotFound { address });
+            }
+            ConfigChange::RemoveHook { address }
+        }
+        ConfigChange::Moderators { moderators } => ConfigChange::Moderators {
+            moderators: moderators
+                .iter()
+                .map(|moderator| Ok(deps.api.addr_validate(moderator)?.to_string()))
+                .collect::<StdResult<Vec<String>>>()?,
+        },
+        ConfigChange::CollectionFee { fee } => {
+            if let Some(fee) = &fee {
+                validate_list_price(deps.storage, fee)?;
+            }
+            ConfigChange::CollectionFee { fee }
+        }
+    };
+    Ok(change)
+}
+
+/// Applies an already validated config change.
+fn apply_config_change(
+    deps: DepsMut,
+    change: ConfigChange,
+)->Result<Response,ContractError>{
+    let mut state = CONFIG.load(deps.storage)?;
+    let mut members = MEMBERS.may_load(deps.storage)?.unwrap_or_default();
+    let mut messages: Vec<CosmosMsg> = vec![];
+
+    let event = match change {
+        ConfigChange::RoyaltyPortion { royalty_portion } => {
+            state.royalty_portion = royalty_portion;
+            Event::new("royalty_portion_changed")
+                .add_attribute("royalty_portion", royalty_portion.to_string())
+        }
+        ConfigChange::MaxRoyaltyPortion { max_royalty_portion } => {
+            state.max_royalty_portion = max_royalty_portion;
+            Event::new("max_royalty_portion_changed")
+                .add_attribute("max_royalty_portion", max_royalty_portion.to_string())
+        }
+        ConfigChange::TokenAddress { address } => {
+            state.token_address = address.clone();
+            Event::new("token_address_changed").add_attribute("address", address)
+        }
+        ConfigChange::NftAddress { address } => {
+            state.nft_address = address.clone();
+            Event::new("nft_address_changed").add_attribute("address", address)
+        }
+        ConfigChange::RemainderAddress { address } => {
+            state.remainder_address = address.clone();
+            Event::new("remainder_address_changed").add_attribute("address", address)
+        }
+        ConfigChange::Treasury { address } => {
+            state.treasury = address.clone();
+            Event::new("treasury_changed").add_attribute("address", address)
+        }
+        ConfigChange::Members { members: new_members } => {
+            members = new_members;
+            Event::new("members_set").add_attribute("count", members.len().to_string())
+        }
+        ConfigChange::AddMember { address, portion } => {
+            members.push(UserInfo {
+                address: address.clone(),
+                portion,
+            });
+            Event::new("member_added")
+                .add_attribute("address", address)
+                .add_attribute("portion", portion.to_string())
+        }
+        ConfigChange::RemoveMember { address } => {
+            members.retain(|user| user.address != address);
+            Event::new("member_removed").add_attribute("address", address)
+        }
+        ConfigChange::UpdateMemberPortion { address, portion } => {
+            if let Some(user) = members.iter_mut().find(|user| user.address == address) {
+                user.portion = portion;
+            }
+            Event::new("member_updated")
+                .add_attribute("address", address)
+                .add_attribute("portion", portion.to_string())
+        }
+        ConfigChange::TimelockDelay { delay } => {
+            state.timelock_delay = delay;
+            Event::new("timelock_delay_changed").add_attribute("delay", delay.to_string())
+        }
+        ConfigChange::FeeTiers { denom, tiers } => {
+            let event = Event::new("fee_tiers_changed")
+                .add_attribute("denom", denom.clone())
+                .add_attribute("count", tiers.len().to_string());
+            if tiers.is_empty() {
+                FEE_TIERS.remove(deps.storage, &denom);
+            } else {
+                FEE_TIERS.save(deps.storage, &denom, &tiers)?;
+            }
+            event
+        }
+        ConfigChange::HopeDiscount { hope_discount } => {
+            state.hope_discount = hope_discount;
+            Event::new("hope_discount_changed")
+                .add_attribute("hope_discount", hope_discount.to_string())
+        }
+        ConfigChange::VolumeWindow { window } => {
+            state.volume_window = window;
+            Event::new("volume_window_changed").add_attribute("window", window.to_string())
+        }
+        ConfigChange::HookGasLimit { gas_limit } => {
+            state.hook_gas_limit = gas_limit;
+            Event::new("hook_gas_limit_changed").add_attribute("gas_limit", gas_limit.to_string())
+        }
+        ConfigChange::RegisterCollection { address } => {
+            let collection = match COLLECTIONS.may_load(deps.storage, &address)? {
+                Some(mut collection) => {
+                    // registering approves a pending submission, so its fee is settled
+                    if let Some(fee) = collection.fee_paid.take() {
+                        messages = payout_msgs(&state, &fee.denom, vec![Payout { recipient: state.treasury.clone(), amount: fee.amount }])?;
+                    }
+                    CollectionInfo {
+                        status: CollectionStatus::Approved,
+                        trading_enabled: true,
+                        ..collection
+                    }
+                }
+                None => CollectionInfo {
+                    name: String::new(),
+                    description: String::new(),
+                    creator: state.owner.clone(),
+                    verified: false,
+                    trading_enabled: true,
+                    status: CollectionStatus::Approved,
+                    fee_paid: None,
+                },
+            };
+            COLLECTIONS.save(deps.storage, &address, &collection)?;
+            Event::new("collection_registered").add_attribute("address", address)
+        }
+        ConfigChange::DeregisterCollection { address } => {
+            // a submission still under review gets its fee back
+            if let Some(CollectionInfo { creator, fee_paid: Some(fee), .. }) = COLLECTIONS.may_load(deps.storage, &address)? {
+                messages = payout_msgs(&state, &fee.denom, vec![Payout { recipient: creator, amount: fee.amount }])?;
+            }
+            COLLECTIONS.remove(deps.storage, &address);
+            Event::new("collection_deregistered").add_attribute("address", address)
+        }
+        ConfigChange::SetDenom { denom, info } => {
+            DENOMS.save(deps.storage, &denom, &info)?;
+            Event::new("denom_set")
+                .add_attribute("denom", denom)
+                .add_attribute("min_price", info.min_price.to_string())
+        }
+        ConfigChange::RemoveDenom { denom } => {
+            DENOMS.remove(deps.storage, &denom);
+            Event::new("denom_removed").add_attribute("denom", denom)
+        }
+        ConfigChange::Curators { curators } => {
+            let event = Event::new("curators_changed").add_attribute("count", curators.len().to_string());
+            CURATORS.save(deps.storage, &curators)?;
+            event
+        }
+        ConfigChange::Referrer { address, share } => {
+            let event = Event::new("referrer_changed").add_attribute("address", address.clone());
+            match share {
+                Some(share) => {
+                    REFERRERS.save(deps.storage, &address, &share)?;
+                    event.add_attribute("share", share.to_string())
+                }
+                None => {
+                    REFERRERS.remove(deps.storage, &address);
+                    event.add_attribute("share", "none")
+                }
+            }
+        }
+        ConfigChange::AddHook { address } => {
+            let mut hooks = HOOKS.may_load(deps.storage)?.unwrap_or_default();
+            hooks.push(address.clone());
+            HOOKS.save(deps.storage, &hooks)?;
+            Event::new("hook_added").add_attribute("address", address)
+        }
+        ConfigChange::RemoveHook { address } => {
+            let mut hooks = HOOKS.may_load(deps.storage)?.unwrap_or_default();
+            hooks.retain(|hook| *hook != address);
+            HOOKS.save(deps.storage, &hooks)?;
+            Event::new("hook_removed").add_attribute("address", address)
+        }
+        ConfigChange::Moderators { moderators } => {
+            let event = Event::new("moderators_changed").add_attribute("count", moderators.len().to_string());
+            MODERATORS.save(deps.storage, &moderators)?;
+            event
+        }
+        ConfigChange::CollectionFee { fee } => {
+            let event = Event::new("collection_fee_changed").add_attribute(
+                "fee",
+                fee.as_ref().map_or_else(|| "none".to_string(), |fee| format!("{}{}", fee.amount, fee.denom)),
+            );
+            state.collection_fee = fee;
+            event
+        }
+    };
+
+    // changes queued together were only checked against the split of their time
+    total_weight(members.iter().map(|user| user.portion))?;
+    CONFIG.save(deps.storage, &state)?;
+    MEMBERS.save(deps.storage, &members)?;
+    Ok(Response::new().add_event(event).add_messages(messages))
+}
+
+fn execute_change_owner(
+    deps: DepsMut,
+    _env:Env,
+    info: MessageInfo,
+    address: String,
+) -> Result<Response, ContractError> {
+    let mut state = CONFIG.load(deps.storage)?;
+
+    if info.sender != state.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    deps.api.addr_validate(&address)?;
+    state.owner = address;
+    CONFIG.save(deps.storage,&state)?;
+    Ok(Response::default())
+}
+
+
+/// Validates addresses of a weighted split and rejects zero or duplicate
+/// entries.
+fn validate_members(deps: Deps, members: Vec<UserInfo>) -> Result<Vec<UserInfo>, ContractError> {
+    let mut validated: Vec<UserInfo> = vec![];
+    for item in members {
+        let address = deps.api.addr_validate(&item.address)?.to_string();
+        if item.portion.is_zero() {
+            return Err(ContractError::ZeroPortion {});
+        }
+        if validated.iter().any(|user| user.address == address) {
+            return Err(ContractError::DuplicateMember { address });
+        }
+        validated.push(UserInfo {
+            address,
+            portion: item.portion,
+        });
+    }
+    total_weight(validated.iter().map(|user| user.portion))?;
+    Ok(validated)
+}
+
+/// Sum of the raw portion weights, which `compute_payouts` divides by. Splits
+/// whose sum overflows are rejected here so payouts can't fail on it.
+fn total_weight(mut portions: impl Iterator<Item = Decimal>) -> Result<u128, ContractError> {
+    portions.try_fold(0u128, |total, portion| {
+        total
+            .checked_add(portion.numerator())
+            .ok_or(ContractError::WeightOverflow {})
+    })
+}
+
+fn validate_timelock_delay(delay: u64) -> Result<(), ContractError> {
+    if delay > MAX_TIMELOCK_DELAY {
+        return Err(ContractError::TimelockTooLong { delay, max_delay: MAX_TIMELOCK_DELAY });
+    }
+    Ok(())
+}
+
+/// Fee portions may never exceed the configured maximum, which itself may
+/// never exceed the whole price.
+fn validate_royalty_portion(
+    royalty_portion: Decimal,
+    max_royalty_portion: Decimal,
+) -> Result<(), ContractError> {
+    if max_royalty_portion > Decimal::one() {
+        return Err(ContractError::WrongPortionError {});
+    }
+    if royalty_portion > max_royalty_portion {
+        return Err(ContractError::RoyaltyTooHigh {
+            royalty_portion,
+            max_royalty_portion,
+        });
+    }
+    Ok(())
+}
+
+
+#[entry_point]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    if msg.id == HOOK_REPLY_ID {
+        // a broken hook must not revert the trade that triggered it
+        return Ok(match msg.result {
+            ContractResult::Err(error) => Response::new()
+                .add_attribute("action", "hook_failed")
+                .add_attribute("error", error),
+            ContractResult::Ok(_) => Response::default(),
+        });
+    }
+
+    let pending = match PENDING_REPLIES.may_load(deps.storage, U64Key::new(msg.id))? {
+        Some(pending) => pending,
+        None => return Err(StdError::generic_err(format!("unknown reply id {}", msg.id)).into()),
+    };
+    PENDING_REPLIES.remove(deps.storage, U64Key::new(msg.id));
+
+    match (pending, msg.result) {
+        // deliveries only reply on success; a failed transfer reverts the sale
+        (PendingReply::Delivery { offering_id }, _) => confirm_delivery(deps, env, offering_id),
+        (PendingReply::Payout { .. }, ContractResult::Ok(_)) => Ok(Response::default()),
+        (PendingReply::Payout { recipient, asset }, ContractResult::Err(error)) => {
+            CLAIMABLE.update(deps.storage, (&recipient, &asset.denom), |balance| -> StdResult<_> {
+                Ok(balance.unwrap_or_default() + asset.amount)
+            })?;
+            CLAIMABLE_TOTALS.update(deps.storage, &asset.denom, |total| -> StdResult<_> {
+                Ok(total.unwrap_or_default() + asset.amount)
+            })?;
+            Ok(Response::new()
+                .add_attribute("action", "payout_failed")
+                .add_attribute("recipient", recipient)
+                .add_attribute("amount", format!("{}{}", asset.amount, asset.denom))
+                .add_attribute("error", error))
+        }
+    }
+}
+
+/// Records the sale once every token of the offering has been delivered.
+fn confirm_delivery(deps: DepsMut, env: Env, offering_id: String) -> Result<Response, ContractError> {
+    let mut settlement = SETTLEMENTS.load(deps.storage, &offering_id)?;
+    settlement.deliveries_left -= 1;
+    if settlement.deliveries_left > 0 {
+        SETTLEMENTS.save(deps.storage, &offering_id, &settlement)?;
+        return Ok(Response::default());
+    }
+    SETTLEMENTS.remove(deps.storage, &offering_id);
+
+    let state = CONFIG.load(deps.storage)?;
+    let price = &settlement.price;
+    record_volume(deps.storage, &env, &state, &settlement.buyer, &price.denom, price.amount)?;
+    record_volume(deps.storage, &env, &state, &settlement.seller, &price.denom, price.amount)?;
+    if let Some(referrer) = &settlement.referrer {
+        REFERRER_EARNINGS.update(deps.storage, (referrer, &price.denom), |total| -> StdResult<_> {
+            Ok(total.unwrap_or_default() + settlement.referrer_earned)
+        })?;
+    }
+    Ok(Response::new()
+        .add_attribute("action", "sale_recorded")
+        .add_attribute("offering_id", offering_id))
+}
+
+fn next_reply_id(storage: &mut dyn Storage) -> StdResult<u64> {
+    let id = REPLY_SEQ.may_load(storage)?.unwrap_or(HOOK_REPLY_ID) + 1;
+    REPLY_SEQ.save(storage, &id)?;
+    Ok(id)
+}
+
+fn execute_rescue_nft(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    nft_address: String,
+    token_id: String,
+    recipient: String,
+) -> Result<Response, ContractError> {
+    let state = CONFIG.load(deps.storage)?;
+    if info.sender != state.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    let held = nft_owner(deps.as_ref(), &nft_address, &token_id)
+        .map_or(false, |owner| owner.owner == env.contract.address.as_ref());
+    if !held {
+        return Err(ContractError::NotInCustody { token_id });
+    }
+    let item = NftItem { nft_address, token_id };
+    if nft_obligated(deps.storage, &item)? {
+        return Err(ContractError::AssetInUse { token_id: item.token_id });
+    }
+
+    let message = nft_delivery_msg(deps.as_ref(), &item.nft_address, &item.token_id, &recipient, None)?;
+    Ok(Response::new()
+        .add_attribute("action", "rescue_nft")
+        .add_attribute("nft_address", item.nft_address)
+        .add_attribute("token_id", item.token_id)
+        .add_message(message))
+}
+
+/// Whether a held token belongs to a listing, a bundle being filled or a
+/// swap proposal.
+fn nft_obligated(storage: &dyn Storage, item: &NftItem) -> StdResult<bool> {
+    if LISTED_TOKENS.has(storage, (&item.nft_address, &item.token_id)) {
+        return Ok(true);
+    }
+    for bundle in BUNDLES.range(storage, None, None, Order::Ascending) {
+        let (_, bundle) = bundle?;
+        if bundle.items.iter().any(|piece| piece.deposited && piece.item == *item) {
+            return Ok(true);
+        }
+    }
+    for swap in SWAPS.range(storage, None, None, Order::Ascending) {
+        let (_, swap) = swap?;
+        if swap.offered.iter().any(|piece| piece.deposited && piece.item == *item) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Funds the contract holds on behalf of users by denom: claimable payouts,
+/// unwithdrawn proceeds, swap sweeteners and collection fees awaiting review.
+fn funds_owed(storage: &dyn Storage) -> StdResult<BTreeMap<String, Uint128>> {
+    let mut owed: BTreeMap<String, Uint128> = BTreeMap::new();
+    let totals = CLAIMABLE_TOTALS
+        .range(storage, None, None, Order::Ascending)
+        .chain(PROCEEDS_TOTALS.range(storage, None, None, Order::Ascending));
+    for total in totals {
+        let (denom, amount) = total?;
+        *owed.entry(String::from_utf8(denom)?).or_default() += amount;
+    }
+    for swap in SWAPS.range(storage, None, None, Order::Ascending) {
+        if let Some(sweetener) = swap?.1.sweetener {
+            *owed.entry(sweetener.denom).or_default() += sweetener.amount;
+        }
+    }
+    for collection in COLLECTIONS.range(storage, None, None, Order::Ascending) {
+        if let Some(fee) = collection?.1.fee_paid {
+            *owed.entry(fee.denom).or_default() += fee.amount;
+        }
+    }
+    Ok(owed.into_iter().filter(|(_, amount)| !amount.is_zero()).collect())
+}
+
+/// Tokens the contract holds on behalf of users: escrowed listings and the
+/// pieces deposited for bundles and swaps.
+fn nfts_owed(storage: &dyn Storage) -> StdResult<Vec<NftItem>> {
+    let mut owed = vec![];
+    for offering in OFFERINGS.range(storage, None, None, Order::Ascending) {
+        let (_, off) = offering?;
+        if off.custody == Custody::Escrow {
+            owed.extend(off.items());
+        }
+    }
+    for bundle in BUNDLES.range(storage, None, None, Order::Ascending) {
+        owed.extend(bundle?.1.items.into_iter().filter(|piece| piece.deposited).map(|piece| piece.item));
+    }
+    for swap in SWAPS.range(storage, None, None, Order::Ascending) {
+        owed.extend(swap?.1.offered.into_iter().filter(|piece| piece.deposited).map(|piece| piece.item));
+    }
+    Ok(owed)
+}
+
+/// The contract's balance in `denom`, read from the HOPE token for HOPE.
+fn held_balance(deps: Deps, env: &Env, state: &State, denom: &str) -> StdResult<Uint128> {
+    if denom == HOPE_DENOM {
+        let balance: BalanceResponse = deps.querier.query_wasm_smart(&state.token_address, &Cw20QueryMsg::Balance {
+            address: env.contract.address.to_string(),
+        })?;
+        return Ok(balance.balance);
+    }
+    Ok(deps.querier.query_balance(&env.contract.address, denom)?.amount)
+}
+
+/// Fails unless `amount` of `balance` is left after the funds owed in `denom`.
+fn ensure_rescuable(storage: &dyn Storage, denom: &str, balance: Uint128, amount: Uint128) -> Result<(), ContractError> {
+    let owed = funds_owed(storage)?.remove(denom).unwrap_or_default();
+    let available = balance.saturating_sub(owed);
+    if amount.is_zero() || amount > available {
+        return Err(ContractError::RescueExceedsFree { denom: denom.to_string(), available });
+    }
+    Ok(())
+}
+
+fn execute_rescue_coins(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    denom: String,
+    amount: Uint128,
+    recipient: String,
+) -> Result<Response, ContractError> {
+    let state = CONFIG.load(deps.storage)?;
+    if info.sender != state.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    let balance = deps.querier.query_balance(&env.contract.address, &denom)?.amount;
+    ensure_rescuable(deps.storage, &denom, balance, amount)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "rescue_coins")
+        .add_attribute("amount", format!("{}{}", amount, denom))
+        .add_message(BankMsg::Send {
+            to_address: deps.api.addr_validate(&recipient)?.to_string(),
+            amount: vec![Coin { denom, amount }],
+        }))
+}
+
+fn execute_rescue_cw20(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token_address: String,
+    amount: Uint128,
+    recipient: String,
+) -> Result<Response, ContractError> {
+    let state = CONFIG.load(deps.storage)?;
+    if info.sender != state.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    let token_address = deps.api.addr_validate(&token_address)?;
+    let balance: BalanceResponse = deps.querier.query_wasm_smart(&token_address, &Cw20QueryMsg::Balance {
+        address: env.contract.address.to_string(),
+    })?;
+    // HOPE obligations are kept under its denom, other tokens are never owed
+    let denom = if token_address == state.token_address { HOPE_DENOM } else { token_address.as_str() };
+    ensure_rescuable(deps.storage, denom, balance.balance, amount)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "rescue_cw20")
+        .add_attribute("token_address", token_address.as_str())
+        .add_attribute("amount", amount)
+        .add_message(WasmMsg::Execute {
+            contract_addr: token_address.into_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: deps.api.addr_validate(&recipient)?.to_string(),
+                amount,
+            })?,
+        }))
+}
+
+fn credit_proceeds(storage: &mut dyn Storage, seller: &str, denom: &str, amount: Uint128) -> StdResult<()> {
+    PROCEEDS.update(storage, (seller, denom), |balance| -> StdResult<_> {
+        Ok(balance.unwrap_or_default() + amount)
+    })?;
+    PROCEEDS_TOTALS.update(storage, denom, |total| -> StdResult<_> {
+        Ok(total.unwrap_or_default() + amount)
+    })?;
+    Ok(())
+}
+
+fn execute_set_pull_proceeds(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    pull: bool,
+) -> Result<Response, ContractError> {
+    if pull {
+        PULL_PROCEEDS.save(deps.storage, info.sender.as_str(), &true)?;
+    } else {
+        PULL_PROCEEDS.remove(deps.storage, info.sender.as_str());
+    }
+    Ok(Response::new()
+        .add_attribute("action", "set_pull_proceeds")
+        .add_attribute("pull", pull.to_string()))
+}
+
+fn execute_withdraw_proceeds(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    asset: Asset,
+) -> Result<Response, ContractError> {
+    let state = CONFIG.load(deps.storage)?;
+    let balance = PROCEEDS
+        .may_load(deps.storage, (info.sender.as_str(), &asset.denom))?
+        .unwrap_or_default();
+    if asset.amount.is_zero() {
+        return Err(ContractError::NoData {});
+    }
+    if asset.amount > balance {
+        return Err(ContractError::NotEnoughFunds {});
+    }
+    if asset.amount == balance {
+        PROCEEDS.remove(deps.storage, (info.sender.as_str(), &asset.denom));
+    } else {
+        PROCEEDS.save(deps.storage, (info.sender.as_str(), &asset.denom), &(balance - asset.amount))?;
+    }
+    PROCEEDS_TOTALS.update(deps.storage, &asset.denom, |total| -> StdResult<_> {
+        Ok(total.unwrap_or_default() - asset.amount)
+    })?;
+
+    let messages = payout_msgs(&state, &asset.denom, vec![Payout {
+        recipient: info.sender.to_string(),
+        amount: asset.amount,
+    }])?;
+    Ok(Response::new()
+        .add_attribute("action", "withdraw_proceeds")
+        .add_attribute("amount", format!("{}{}", asset.amount, asset.denom))
+        .add_messages(messages))
+}
+
+fn execute_claim_balance(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    denom: String,
+) -> Result<Response, ContractError> {
+    let state = CONFIG.load(deps.storage)?;
+    let amount = CLAIMABLE
+        .may_load(deps.storage, (info.sender.as_str(), &denom))?
+        .unwrap_or_default();
+    if amount.is_zero() {
+        return Err(ContractError::NoData {});
+    }
+    CLAIMABLE.remove(deps.storage, (info.sender.as_str(), &denom));
+    CLAIMABLE_TOTALS.update(deps.storage, &denom, |total| -> StdResult<_> {
+        Ok(total.unwrap_or_default() - amount)
+    })?;
+
+    let messages = payout_msgs(&state, &denom, vec![Payout {
+        recipient: info.sender.to_string(),
+        amount,
+    }])?;
+    Ok(Response::new()
+        .add_attribute("action", "claim_balance")
+        .add_attribute("amount", format!("{}{}", amount, denom))
+        .add_messages(messages))
+}
+
+#[entry_point]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::GetStateInfo {} => to_binary(&query_state_info(deps)?),
+        QueryMsg::GetOfferings { reserved_for, storefront } => to_binary(&query_get_offerings(deps, reserved_for, storefront)?),
+        QueryMsg::GetMembers {} => to_binary(&query_get_members(deps)?),
+        QueryMsg::GetPendingChanges {} => to_binary(&query_pending_changes(deps)?),
+        QueryMsg::GetBundle { bundle_id } => to_binary(&BUNDLES.load(deps.storage, &bundle_id)?),
+        QueryMsg::GetSwap { swap_id } => to_binary(&SWAPS.load(deps.storage, &swap_id)?),
+        QueryMsg::GetDenoms {} => to_binary(&query_denoms(deps)?),
+        QueryMsg::GetCollection { address } => to_binary(&COLLECTIONS.load(deps.storage, &address)?),
+        QueryMsg::GetCollections { status, start_after, limit } => to_binary(&query_collections(deps, status, start_after, limit)?),
+        QueryMsg::GetReferrer { address } => to_binary(&query_referrer(deps, address)?),
+        QueryMsg::GetStorefront { storefront_id } => to_binary(&STOREFRONTS.load(deps.storage, &storefront_id)?),
+        QueryMsg::GetStorefronts { start_after, limit } => to_binary(&query_storefronts(deps, start_after, limit)?),
+        QueryMsg::Solvency {} => to_binary(&query_solvency(deps, env)?),
+        QueryMsg::GetProceeds { address } => to_binary(&query_proceeds(deps, address)?),
+        QueryMsg::GetClaimable { address } => to_binary(&query_claimable(deps, address)?),
+        QueryMsg::GetHooks {} => to_binary(&HooksResponse {
+            hooks: HOOKS.may_load(deps.storage)?.unwrap_or_default(),
+        }),
+        QueryMsg::GetModerators {} => to_binary(&ModeratorsResponse {
+            moderators: MODERATORS.may_load(deps.storage)?.unwrap_or_default(),
+        }),
+        QueryMsg::GetFlagLog { start_after, limit } => to_binary(&query_flag_log(deps, start_after, limit)?),
+        QueryMsg::GetCurators {} => to_binary(&CuratorsResponse {
+            curators: CURATORS.may_load(deps.storage)?.unwrap_or_default(),
+        }),
+        QueryMsg::FeeFor { address, asset } => to_binary(&query_fee_for(deps, env, address, asset)?),
+    }
+}
+
+pub fn query_state_info(deps:Deps) -> StdResult<State>{
+    let state =  CONFIG.load(deps.storage)?;
+    Ok(state)
+}
+
+pub fn query_get_members(deps:Deps) -> StdResult<Vec<UserInfo>>{
+    let members = MEMBERS.may_load(deps.storage)?.unwrap_or_default();
+    Ok(members)
+}
+
+pub fn query_fee_for(deps:Deps, env:Env, address:String, asset:String) -> StdResult<FeeForResponse>{
+    let state = CONFIG.load(deps.storage)?;
+    let volume = rolling_volume(deps.storage, &env, &state, &address, &asset)?;
+    let royalty_portion = effective_royalty_portion(
+        deps,
+        &env,
+        &state,
+        state.royalty_portion,
+        &address,
+        &asset,
+    )?;
+    Ok(FeeForResponse { royalty_portion, volume })
+}
+
+pub fn query_pending_changes(deps:Deps) -> StdResult<PendingChangesResponse>{
+    let changes: StdResult<Vec<QueryPendingChangeResult>> = PENDING_CHANGES
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (k, pending) = item?;
+            Ok(QueryPendingChangeResult {
+                change_id: from_utf8(&k)?.to_string(),
+                change: pending.change,
+                executable_at: pending.executable_at,
+            })
+        })
+        .collect();
+    Ok(PendingChangesResponse { changes: changes? })
+}
+
+pub fn query_denoms(deps:Deps) -> StdResult<DenomsResponse>{
+    let denoms: StdResult<Vec<QueryDenomResult>> = DENOMS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (k, info) = item?;
+            Ok(QueryDenomResult {
+                denom: from_utf8(&k)?.to_string(),
+                symbol: info.symbol,
+                decimals: info.decimals,
+                min_price: info.min_price,
+            })
+        })
+        .collect();
+    Ok(DenomsResponse { denoms: denoms? })
+}
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+pub fn query_collections(
+    deps:Deps,
+    status:Option<CollectionStatus>,
+    start_after:Option<String>,
+    limit:Option<u32>,
+) -> StdResult<CollectionsResponse>{
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let collections: StdResult<Vec<QueryCollectionResult>> = COLLECTIONS
+        .range(deps.storage, start, None, Order::Ascending)
+        .filter(|item| match (item, &status) {
+            (Ok((_, info)), Some(status)) => info.status == *status,
+            _ => true,
+        })
+        .take(limit)
+        .map(|item| {
+            let (k, info) = item?;
+            Ok(QueryCollectionResult {
+                address: from_utf8(&k)?.to_string(),
+                info,
+            })
+        })
+        .collect();
+    Ok(CollectionsResponse { collections: collections? })
+}
+
+pub fn query_storefronts(deps:Deps, start_after:Option<String>, limit:Option<u32>) -> StdResult<StorefrontsResponse>{
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let storefronts: StdResult<Vec<QueryStorefrontResult>> = STOREFRONTS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (k, storefront) = item?;
+            Ok(QueryStorefrontResult {
+                id: from_utf8(&k)?.to_string(),
+                storefront,
+            })
+        })
+        .collect();
+    Ok(StorefrontsResponse { storefronts: storefronts? })
+}
+
+pub fn query_solvency(deps:Deps, env:Env) -> StdResult<SolvencyResponse>{
+    let state = CONFIG.load(deps.storage)?;
+    let funds = funds_owed(deps.storage)?
+        .into_iter()
+        .map(|(denom, owed)| {
+            let held = held_balance(deps, &env, &state, &denom)?;
+            Ok(FundsSolvency { shortfall: owed.saturating_sub(held), denom, owed, held })
+        })
+        .collect::<StdResult<Vec<FundsSolvency>>>()?;
+    // a burned or moved token fails the owner query either way
+    let missing_nfts: Vec<NftItem> = nfts_owed(deps.storage)?
+        .into_iter()
+        .filter(|item| {
+            nft_owner(deps, &item.nft_address, &item.token_id)
+                .map_or(true, |owner| owner.owner != env.contract.address.as_ref())
+        })
+        .collect();
+    Ok(SolvencyResponse {
+        solvent: missing_nfts.is_empty() && funds.iter().all(|funds| funds.shortfall.is_zero()),
+        funds,
+        missing_nfts,
+    })
+}
+
+pub fn query_proceeds(deps:Deps, address:String) -> StdResult<ProceedsResponse>{
+    let balances: StdResult<Vec<Asset>> = PROCEEDS
+        .prefix(&address)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (k, amount) = item?;
+            Ok(Asset { denom: from_utf8(&k)?.to_string(), amount })
+        })
+        .collect();
+    Ok(ProceedsResponse {
+        pull: PULL_PROCEEDS.has(deps.storage, &address),
+        balances: balances?,
+    })
+}
+
+pub fn query_claimable(deps:Deps, address:String) -> StdResult<ClaimableResponse>{
+    let balances: StdResult<Vec<Asset>> = CLAIMABLE
+        .prefix(&address)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (k, amount) = item?;
+            Ok(Asset { denom: from_utf8(&k)?.to_string(), amount })
+        })
+        .collect();
+    Ok(ClaimableResponse { balances: balances? })
+}
+
+pub fn query_referrer(deps:Deps, address:String) -> StdResult<ReferrerResponse>{
+    let earned: StdResult<Vec<Asset>> = REFERRER_EARNINGS
+        .prefix(&address)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (k, amount) = item?;
+            Ok(Asset { denom: from_utf8(&k)?.to_string(), amount })
+        })
+        .collect();
+    Ok(ReferrerResponse {
+        share: REFERRERS.may_load(deps.storage, &address)?,
+        earned: earned?,
+    })
+}
+
+pub fn query_flag_log(deps:Deps, start_after:Option<u64>, limit:Option<u32>) -> StdResult<FlagLogResponse>{
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive_int);
+    let flags: StdResult<Vec<QueryFlagResult>> = FLAG_LOG
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (k, record) = item?;
+            let id: [u8; 8] = k.as_slice().try_into().map_err(|_| StdError::generic_err("invalid flag id"))?;
+            Ok(QueryFlagResult { id: u64::from_be_bytes(id), record })
+        })
+        .collect();
+    Ok(FlagLogResponse { flags: flags? })
+}
+
+/// Public offerings, plus the private ones reserved for `reserved_for`.
+pub fn query_get_offerings(
+    deps:Deps,
+    reserved_for:Option<String>,
+    storefront:Option<String>,
+) -> StdResult<OfferingsResponse>{
+    let res: StdResult<Vec<QueryOfferingsResult>> = OFFERINGS
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter(|kv_item| match kv_item {
+            Ok((_, offering)) => (offering.reserved_for.is_empty()
+                || reserved_for.as_ref().map_or(false, |buyer| offering.reserved_for_buyer(buyer)))
+                && (storefront.is_none() || offering.storefront == storefront),
+            Err(_) => true,
+        })
+        .map(|kv_item| parse_offering(deps, kv_item))
+        .collect();
+    Ok(OfferingsResponse {
+        offerings: res?, // Placeholder
+    })
+}
+
+fn parse_offering(
+    deps:Deps,
+    item: StdResult<Pair<Offering>>,
+) -> StdResult<QueryOfferingsResult> {
+    item.and_then(|(k, offering)| {
+        let id = from_utf8(&k)?;
+        Ok(QueryOfferingsResult {
+            id: id.to_string(),
+            token_id: offering.token_id,
+            list_price: offering.list_price,
+            seller: deps.api.addr_validate(&offering.seller)?.to_string(),
+            royalty_portion: offering.royalty_portion,
+            custody: offering.custody,
+            reserved_for: offering.reserved_for,
+            nft_address: offering.nft_address,
+            bundle: offering.bundle,
+            alternative_prices: offering.alternative_prices,
+            storefront: offering.storefront,
+            storefront_portion: offering.storefront_portion,
+            pull_proceeds: offering.pull_proceeds,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{mock_env, mock_info, MockApi, MockStorage, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{ CosmosMsg, Coin, OwnedDeps, Querier, ReplyOn, SubMsgExecutionResponse};
+    use crate::state::{FeeTier, NftItem};
+    use crate::msg::BuyMany;
+    use crate::mock_querier::{mock_dependencies_with_nfts, WasmMockQuerier};
+
+    /// Registers a native listing denom without a minimum.
+    fn accept_denom(deps: DepsMut, denom: &str) {
+        let info = DenomInfo { symbol: denom.to_uppercase(), decimals: 6, min_price: Uint128::zero() };
+        DENOMS.save(deps.storage, denom, &info).unwrap();
+    }
+
+    /// Executes `msg` and, when it succeeds, moves the sent funds and the
+    /// outgoing messages' assets like the chain would before checking that
+    /// the contract still holds everything it owes.
+    fn execute_checked(
+        deps: &mut OwnedDeps<MockStorage, MockApi, WasmMockQuerier>,
+        env: Env,
+        info: MessageInfo,
+        msg: ExecuteMsg,
+    ) -> Result<Response, ContractError> {
+        let received = match &msg {
+            ExecuteMsg::Receive(rcv_msg) => rcv_msg.amount,
+            _ => Uint128::zero(),
+        };
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg)?;
+        deps.querier.receive_coins(&info.funds);
+        deps.querier.receive_tokens(info.sender.as_str(), received);
+        deps.querier.apply_messages(&res.messages);
+        let solvency = query_solvency(deps.as_ref(), env).unwrap();
+        assert!(solvency.solvent, "{:?}", solvency);
+        Ok(res)
+    }
+
+    /// Replies to every submessage of `res` that waits for success, as the
+    /// chain does once they have executed.
+    fn confirm_submessages<Q: Querier>(deps: &mut OwnedDeps<MockStorage, MockApi, Q>, res: &Response) {
+        for sub in &res.messages {
+            if matches!(sub.reply_on, ReplyOn::Success | ReplyOn::Always) {
+                let result = ContractResult::Ok(SubMsgExecutionResponse { events: vec![], data: None });
+                reply(deps.as_mut(), mock_env(), Reply { id: sub.id, result }).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn testing() {
+        //Instantiate
+        let mut deps = mock_dependencies_with_nfts(&[]);
+        deps.querier.set_default_nft_owner(MOCK_CONTRACT_ADDR);
+        let instantiate_msg = InstantiateMsg {
+            royalty_portion:Decimal::from_ratio(2u128, 100u128),
+            remainder_address:None,
+            max_royalty_portion:None,
+            treasury:None,
+            timelock_delay:None
+        };
+        let info = mock_info("creator", &[]);
+        let res = instantiate(deps.as_mut(), mock_env(), info, instantiate_msg).unwrap();
+        accept_denom(deps.as_mut(), "ujuno");
+        assert_eq!(0, res.messages.len());
+        let state = query_state_info(deps.as_ref()).unwrap();
+        assert_eq!(state.owner,"creator".to_string());
+        assert_eq!(state.royalty_portion,Decimal::from_ratio(2u128, 100u128));
+
+        //Change Owner
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::ChangeOwner { address:"owner".to_string()};
+        execute_checked(&mut deps, mock_env(), info, msg).unwrap();
+        let state = query_state_info(deps.as_ref()).unwrap();
+        assert_eq!(state.owner,"owner".to_string());
+
+        //Set Members
+
+        let info = mock_info("owner", &[]);
+        let msg = ExecuteMsg::SetAdminsList { members: vec![UserInfo{
+            address:"admin1".to_string(),
+            portion:Decimal::from_ratio(3u128, 10u128)
+        },UserInfo{
+            address:"admin2".to_string(),
+            portion:Decimal::from_ratio(7u128, 10u128)
+        }] }; 
+        execute_checked(&mut deps, mock_env(), info, msg).unwrap();
+
+        let members = query_get_members(deps.as_ref()).unwrap();
+        assert_eq!(members, vec![UserInfo{
+            address:"admin1".to_string(),
+            portion:Decimal::from_ratio(3u128, 10u128)
+        },UserInfo{
+            address:"admin2".to_string(),
+            portion:Decimal::from_ratio(7u128, 10u128)
+        }]);
+     
+        //Chage Portion
+
+        let info = mock_info("owner", &[]);
+        let msg = ExecuteMsg::ChangeRoyaltyPortion { royalty_portion: Decimal::from_ratio(3u128, 100u128) };
+        execute_checked(&mut deps, mock_env(), info, msg).unwrap();
+        let state = query_state_info(deps.as_ref()).unwrap();
+        assert_eq!(state.royalty_portion,Decimal::from_ratio(3u128, 100u128));
+        
+        //Change Token Contract Address
+
+        let info = mock_info("owner", &[]);
+        let msg = ExecuteMsg::SetTokenAddress  { address:"token_address1".to_string()};
+        execute_checked(&mut deps, mock_env(), info, msg).unwrap();
+        let state = query_state_info(deps.as_ref()).unwrap();
+        assert_eq!(state.token_address,"token_address1".to_string());
+
+        //Change NFT contract Address
+
+        let info = mock_info("owner", &[]);
+        let msg = ExecuteMsg::SetNftAddress  { address:"nft_address1".to_string()};
+        execute_checked(&mut deps, mock_env(), info, msg).unwrap();
+        let state = query_state_info(deps.as_ref()).unwrap();
+        assert_eq!(state.nft_address,"nft_address1".to_string());
+        
+        //Send NFT to marketplace contract
+
+        let cw721_msg = SellNft{
+            list_price:Asset{
+                denom:"ujuno".to_string(),
+                amount:Uint128::new(2)
+            },
+            reserved_for:None,
+            alternative_prices:None,
+            storefront:None,
+            pull_proceeds:None
+        };
+
+        let info = mock_info("nft_address1", &[]);
+        let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg{
+            sender:"owner1".to_string(),
+            token_id:"Hope.1".to_string(),
+            msg:to_binary(&cw721_msg).unwrap()
+        });
+        let res = execute_checked(&mut deps, mock_env(), info.clone(), msg).unwrap();
+        assert_eq!(0,res.messages.len());
+
+        let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg{
+            sender:"owner2".to_string(),
+            token_id:"Hope.2".to_string(),
+            msg:to_binary(&cw721_msg).unwrap()
+        });
+        let res = execute_checked(&mut deps, mock_env(), info, msg).unwrap();
+        assert_eq!(0,res.messages.len());
+
+        let nft_market_datas = query_get_offerings(deps.as_ref(), None, None).unwrap();
+        assert_eq!(nft_market_datas.offerings,
+            vec![
+                QueryOfferingsResult{
+                    id :"1".to_string(),
+                    token_id:"Hope.1".to_string(),
+                    seller : "owner1".to_string(),
+                    list_price:Asset { 
+                        denom: "ujuno".to_string(),
+                        amount: Uint128::new(2) 
+                    },
+                    royalty_portion:Decimal::from_ratio(3u128, 100u128),
+                    custody:Custody::Escrow,
+                    reserved_for:vec![],
+                    nft_address:"nft_address1".to_string(),
+                    bundle:vec![],
+                    alternative_prices:vec![],
+                    storefront:None,
+                    storefront_portion:Decimal::zero(),
+                    pull_proceeds:false
+                },
+                QueryOfferingsResult{
+                    id :"2".to_string(),
+                    token_id:"Hope.2".to_string(),
+                    seller : "owner2".to_string(),
+                    list_price:Asset { 
+                        denom: "ujuno".to_string(),
+                        amount: Uint128::new(2) 
+                    },
+                    royalty_portion:Decimal::from_ratio(3u128, 100u128),
+                    custody:Custody::Escrow,
+                    reserved_for:vec![],
+                    nft_address:"nft_address1".to_string(),
+                    bundle:vec![],
+                    alternative_prices:vec![],
+                    storefront:None,
+                    storefront_portion:Decimal::zero(),
+                    pull_proceeds:false
+                }
+            ]
+        );
+
+        //Withdraw nft from market place
+
+        let info = mock_info("owner1", &[]);
+        let msg = ExecuteMsg::WithdrawNft { offering_id: "1".to_string() };
+        let res = execute_checked(&mut deps, mock_env(), info.clone(), msg).unwrap();
+        assert_eq!(1,res.messages.len());
+        assert_eq!(res.messages[0].msg, CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "nft_address1".to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw721ExecuteMsg::TransferNft {
+                    recipient: "owner1".to_string(),
+                    token_id: "Hope.1".to_string(),
+            }).unwrap(),
+        }));      
+        
+        let nft_market_datas = query_get_offerings(deps.as_ref(), None, None).unwrap();
+        assert_eq!(nft_market_datas.offerings,
+            vec![
+                QueryOfferingsResult{
+                    id :"2".to_string(),
+                    token_id:"Hope.2".to_string(),
+                    seller : "owner2".to_string(),
+                    list_price:Asset { 
+                        denom: "ujuno".to_string(),
+                        amount: Uint128::new(2) 
+                    },
+                    royalty_portion:Decimal::from_ratio(3u128, 100u128),
+                    custody:Custody::Escrow,
+                    reserved_for:vec![],
+                    nft_address:"nft_address1".to_string(),
+                    bundle:vec![],
+                    alternative_prices:vec![],
+                    storefront:None,
+                    storefront_portion:Decimal::zero(),
+                    pull_proceeds:false
+                }
+            ]
+        );
+
+        //Send NFT to marketplace contract
+
+        let cw721_msg = SellNft{
+            list_price:Asset{
+                denom:"hope".to_string(),
+                amount:Uint128::new(10)
+            },
+            reserved_for:None,
+            alternative_prices:None,
+            storefront:None,
+            pull_proceeds:None
+        };
+
+        let info = mock_info("nft_address1", &[]);
+        let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg{
+            sender:"owner3".to_string(),
+            token_id:"Hope.3".to_string(),
+            msg:to_binary(&cw721_msg).unwrap()
+        });
+        let res = execute_checked(&mut deps, mock_env(), info.clone(), msg).unwrap();
+        assert_eq!(0,res.messages.len());
+
+        let nft_market_datas = query_get_offerings(deps.as_ref(), None, None).unwrap();
+        assert_eq!(nft_market_datas.offerings,
+            vec![            
+                QueryOfferingsResult{
+                    id :"2".to_string(),
+                    token_id:"Hope.2".to_string(),
+                    seller : "owner2".to_string(),
+                    list_price:Asset { 
+                        denom: "ujuno".to_string(),
+                        amount: Uint128::new(2) 
+                    },
+                    royalty_portion:Decimal::from_ratio(3u128, 100u128),
+                    custody:Custody::Escrow,
+                    reserved_for:vec![],
+                    nft_address:"nft_address1".to_string(),
+                    bundle:vec![],
+                    alternative_prices:vec![],
+                    storefront:None,
+                    storefront_portion:Decimal::zero(),
+                    pull_proceeds:false
+                }, QueryOfferingsResult{
+                    id :"3".to_string(),
+                    token_id:"Hope.3".to_string(),
+                    seller : "owner3".to_string(),
+                    list_price:Asset { 
+                        denom: "hope".to_string(),
+                        amount: Uint128::new(10) 
+                    },
+                    royalty_portion:Decimal::from_ratio(3u128, 100u128),
+                    custody:Custody::Escrow,
+                    reserved_for:vec![],
+                    nft_address:"nft_address1".to_string(),
+                    bundle:vec![],
+                    alternative_prices:vec![],
+                    storefront:None,
+                    storefront_portion:Decimal::zero(),
+                    pull_proceeds:false
+                }
+            ]
+        );
+
+        //Buy nft using token
+
+        let cw20_msg = BuyNft{
+             offering_id:"3".to_string(),
+             recipient:None,
+             msg:None,
+             referrer:None
+        };
+
+        let info = mock_info("token_address1", &[]);
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg{
+            sender:"buyer".to_string(),
+            amount:Uint128::new(1000),
+            msg:to_binary(&cw20_msg).unwrap()
+        });
+        let res = execute_checked(&mut deps, mock_env(), info.clone(), msg).unwrap();
+        assert_eq!(4,res.messages.len());
+        assert_eq!(res.messages[0].msg, CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "nft_address1".to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw721ExecuteMsg::TransferNft {
+                    recipient: "buyer".to_string(),
+                    token_id: "Hope.3".to_string(),
+            }).unwrap(),
+        }));
+        assert_eq!(res.messages[1].msg, CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "token_address1".to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "owner3".to_string(),
+                    amount:Uint128::new(970)
+            }).unwrap(),
+        }));
+
+        assert_eq!(res.messages[2].msg.clone(), CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "token_address1".to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "admin1".to_string(),
+                    amount:Uint128::new(9)
+            }).unwrap(),
+        }));
+
+
+        assert_eq!(res.messages[3].msg, CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "token_address1".to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "admin2".to_string(),
+                    amount:Uint128::new(21)
+            }).unwrap(),
+        }));
+
+        let nft_market_datas = query_get_offerings(deps.as_ref(), None, None).unwrap();
+        assert_eq!(nft_market_datas.offerings,
+            vec![            
+                QueryOfferingsResult{
+                    id :"2".to_string(),
+                    token_id:"Hope.2".to_string(),
+                    seller : "owner2".to_string(),
+                    list_price:Asset { 
+                        denom: "ujuno".to_string(),
+                        amount: Uint128::new(2) 
+                    },
+                    royalty_portion:Decimal::from_ratio(3u128, 100u128),
+                    custody:Custody::Escrow,
+                    reserved_for:vec![],
+                    nft_address:"nft_address1".to_string(),
+                    bundle:vec![],
+                    alternative_prices:vec![],
+                    storefront:None,
+                    storefront_portion:Decimal::zero(),
+                    pull_proceeds:false
+                }
+            ]
+        );
+
+        //Buy nft using stable coin 
+
+        let info = mock_info("buyer2", &[Coin{
+            denom:"ujuno".to_string(),
+            amount:Uint128::new(1000)
+        }]);
+        let msg = ExecuteMsg::BuyNft { offering_id: "2".to_string(), recipient: None, msg: None, referrer: None };
+        let res = execute_checked(&mut deps, mock_env(), info.clone(), msg).unwrap();
+        assert_eq!(res.messages.len(),4);
+        assert_eq!(res.messages[0].msg,CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: state.nft_address.to_string(),
+                funds: vec![],
+                msg: to_binary(&Cw721ExecuteMsg::TransferNft {
+                    recipient: "buyer2".to_string(),
+                    token_id: "Hope.2".to_string(),
+            }).unwrap()
+        }));
+        assert_eq!(res.messages[1].msg,CosmosMsg::Bank(BankMsg::Send {
+                to_address: "owner2".to_string(),
+                amount:vec![Coin{
+                    denom:"ujuno".to_string(),
+                    amount:Uint128::new(970)
+                }]
+        }));
+        assert_eq!(res.messages[2].msg,CosmosMsg::Bank(BankMsg::Send {
+                to_address: "admin1".to_string(),
+                amount:vec![Coin{
+                    denom:"ujuno".to_string(),
+                    amount:Uint128::new(9)
+                }]
+        }));
+        assert_eq!(res.messages[3].msg,CosmosMsg::Bank(BankMsg::Send {
+                to_address: "admin2".to_string(),
+                amount:vec![Coin{
+                    denom:"ujuno".to_string(),
+                    amount:Uint128::new(21)
+                }]
+        }));
+
+        let nft_market_datas = query_get_offerings(deps.as_ref(), None, None).unwrap();
+        assert_eq!(nft_market_datas.offerings,
+            vec![]
+        );
+    }
+
+    #[test]
+    fn payouts_add_up_to_amount() {
+        let splits = vec![
+            vec![(1, 1)],
+            vec![(3, 10), (7, 10)],
+            vec![(1, 3), (1, 3), (1, 3)],
+            vec![(1, 7), (2, 7), (4, 7)],
+            vec![(1, 6), (1, 6), (1, 6), (1, 2)],
+        ];
+        let royalties = vec![(0, 100), (1, 100), (3, 100), (7, 100), (1, 3), (1, 1)];
+        let prices: Vec<u128> = vec![0, 1, 2, 3, 7, 10, 33, 99, 100, 101, 997, 1000, 12_345, 999_999_999_999];
+
+        for split in &splits {
+            let members: Vec<UserInfo> = split
+                .iter()
+                .enumerate()
+                .map(|(i, (n, d))| UserInfo {
+                    address: format!("admin{}", i),
+                    portion: Decimal::from_ratio(*n as u128, *d as u128),
+                })
+                .collect();
+            for (n, d) in &royalties {
+                let royalty_portion = Decimal::from_ratio(*n as u128, *d as u128);
+                for price in &prices {
+                    let amount = Uint128::new(*price);
+                    let payouts = compute_payouts(amount, royalty_portion, None, &members, "seller", "dust");
+
+                    let total: Uint128 = payouts.iter().map(|p| p.amount).sum();
+                    assert_eq!(total, amount);
+                    assert_eq!(payouts[0].recipient, "seller".to_string());
+                    assert_eq!(payouts[0].amount, amount - amount * royalty_portion);
+                    let dust = payouts.last().unwrap();
+                    assert_eq!(dust.recipient, "dust".to_string());
+                    assert!(dust.amount.u128() <= members.len() as u128);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn remainder_goes_to_remainder_address() {
+        let mut deps = mock_dependencies_with_nfts(&[]);
+        deps.querier.set_default_nft_owner(MOCK_CONTRACT_ADDR);
+        let instantiate_msg = InstantiateMsg {
+            royalty_portion:Decimal::from_ratio(1u128, 10u128),
+            remainder_address:Some("treasury".to_string()),
+            max_royalty_portion:None,
+            treasury:None,
+            timelock_delay:None
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg).unwrap();
+        accept_denom(deps.as_mut(), "ujuno");
+
+        let msg = ExecuteMsg::SetRemainderAddress { address: "dust".to_string() };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("anyone", &[]), msg.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+        let msg = ExecuteMsg::SetAdminsList { members: vec![UserInfo{
+            address:"admin1".to_string(),
+            portion:Decimal::from_ratio(1u128, 3u128)
+        },UserInfo{
+            address:"admin2".to_string(),
+            portion:Decimal::from_ratio(2u128, 3u128)
+        }] };
+        execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+        let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg{
+            sender:"seller".to_string(),
+            token_id:"Hope.1".to_string(),
+            msg:to_binary(&SellNft{
+                list_price:Asset{
+                    denom:"ujuno".to_string(),
+                    amount:Uint128::new(15)
+                },
+                reserved_for:None,
+                alternative_prices:None,
+                storefront:None,
+                pull_proceeds:None
+            }).unwrap()
+        });
+        execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), msg).unwrap();
+
+        let info = mock_info("buyer", &[Coin{
+            denom:"ujuno".to_string(),
+            amount:Uint128::new(15)
+        }]);
+        let msg = ExecuteMsg::BuyNft { offering_id: "1".to_string(), recipient: None, msg: None, referrer: None };
+        let res = execute_checked(&mut deps, mock_env(), info, msg).unwrap();
+        // fee of 1 splits into 0 and 0, so the whole fee is remainder
+        assert_eq!(res.messages.len(), 3);
+        assert_eq!(res.messages[1].msg,CosmosMsg::Bank(BankMsg::Send {
+                to_address: "seller".to_string(),
+                amount:vec![Coin{
+                    denom:"ujuno".to_string(),
+                    amount:Uint128::new(14)
+                }]
+        }));
+        assert_eq!(res.messages[2].msg,CosmosMsg::Bank(BankMsg::Send {
+                to_address: "dust".to_string(),
+                amount:vec![Coin{
+                    denom:"ujuno".to_string(),
+                    amount:Uint128::new(1)
+                }]
+        }));
+    }
+
+    #[test]
+    fn fee_bounds_and_treasury_fallback() {
+        let mut deps = mock_dependencies_with_nfts(&[]);
+        deps.querier.set_default_nft_owner(MOCK_CONTRACT_ADDR);
+        let instantiate_msg = InstantiateMsg {
+            royalty_portion:Decimal::percent(10),
+            remainder_address:None,
+            max_royalty_portion:Some(Decimal::percent(5)),
+            treasury:Some("treasury".to_string()),
+            timelock_delay:None
+        };
+        let err = instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg).unwrap_err();
+        assert!(matches!(err, ContractError::RoyaltyTooHigh { .. }));
+
+        let instantiate_msg = InstantiateMsg {
+            royalty_portion:Decimal::percent(5),
+            remainder_address:None,
+            max_royalty_portion:Some(Decimal::percent(10)),
+            treasury:Some("treasury".to_string()),
+            timelock_delay:None
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg).unwrap();
+        accept_denom(deps.as_mut(), "ujuno");
+        assert_eq!(query_get_members(deps.as_ref()).unwrap(), vec![]);
+
+        // fees above the maximum are rejected when they are configured
+        let msg = ExecuteMsg::ChangeRoyaltyPortion { royalty_portion: Decimal::percent(150) };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::RoyaltyTooHigh { .. }));
+        let msg = ExecuteMsg::SetMaxRoyaltyPortion { max_royalty_portion: Decimal::percent(200) };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::WrongPortionError {}));
+        let msg = ExecuteMsg::SetMaxRoyaltyPortion { max_royalty_portion: Decimal::percent(4) };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::RoyaltyTooHigh { .. }));
+        let msg = ExecuteMsg::SetMaxRoyaltyPortion { max_royalty_portion: Decimal::percent(20) };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("anyone", &[]), msg.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+        let msg = ExecuteMsg::ChangeRoyaltyPortion { royalty_portion: Decimal::percent(20) };
+        execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+        // without members the whole fee goes to the treasury
+        let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg{
+            sender:"seller".to_string(),
+            token_id:"Hope.1".to_string(),
+            msg:to_binary(&SellNft{
+                list_price:Asset{
+                    denom:"ujuno".to_string(),
+                    amount:Uint128::new(100)
+                },
+                reserved_for:None,
+                alternative_prices:None,
+                storefront:None,
+                pull_proceeds:None
+            }).unwrap()
+        });
+        execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), msg).unwrap();
+
+        let info = mock_info("buyer", &[Coin{
+            denom:"ujuno".to_string(),
+            amount:Uint128::new(100)
+        }]);
+        let msg = ExecuteMsg::BuyNft { offering_id: "1".to_string(), recipient: None, msg: None, referrer: None };
+        let res = execute_checked(&mut deps, mock_env(), info, msg).unwrap();
+        assert_eq!(res.messages.len(), 3);
+        assert_eq!(res.messages[1].msg,CosmosMsg::Bank(BankMsg::Send {
+                to_address: "seller".to_string(),
+                amount:vec![Coin{
+                    denom:"ujuno".to_string(),
+                    amount:Uint128::new(80)
+                }]
+        }));
+        assert_eq!(res.messages[2].msg,CosmosMsg::Bank(BankMsg::Send {
+                to_address: "treasury".to_string(),
+                amount:vec![Coin{
+                    denom:"ujuno".to_string(),
+                    amount:Uint128::new(20)
+                }]
+        }));
+    }
+
+    #[test]
+    fn incremental_member_management() {
+        let mut deps = mock_dependencies_with_nfts(&[]);
+        let instantiate_msg = InstantiateMsg {
+            royalty_portion:Decimal::percent(10),
+            remainder_address:Some("dust".to_string()),
+            max_royalty_portion:None,
+            treasury:None,
+            timelock_delay:None
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg).unwrap();
+        accept_denom(deps.as_mut(), "ujuno");
+
+        let msg = ExecuteMsg::AddMember { address: "admin1".to_string(), portion: Decimal::one() };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("anyone", &[]), msg.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        let res = execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg.clone()).unwrap();
+        assert_eq!(res.events[0].ty, "member_added".to_string());
+        let err = execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::DuplicateMember { .. }));
+
+        let msg = ExecuteMsg::AddMember { address: "admin2".to_string(), portion: Decimal::zero() };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::ZeroPortion {}));
+        let msg = ExecuteMsg::AddMember { address: "admin2".to_string(), portion: Decimal::one() };
+        execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+        let msg = ExecuteMsg::AddMember { address: "admin3".to_string(), portion: Decimal::one() };
+        execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+        let msg = ExecuteMsg::UpdateMemberPortion { address: "admin3".to_string(), portion: Decimal::percent(200) };
+        let res = execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+        assert_eq!(res.events[0].ty, "member_updated".to_string());
+        let msg = ExecuteMsg::RemoveMember { address: "admin1".to_string() };
+        let res = execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg.clone()).unwrap();
+        assert_eq!(res.events[0].ty, "member_removed".to_string());
+        let err = execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::MemberNotFound { .. }));
+
+        // weights whose sum doesn't fit are rejected before they reach a sale
+        let huge = Decimal::from_ratio(u128::MAX / 1_000_000_000_000_000_000u128, 1u128);
+        let msg = ExecuteMsg::UpdateMemberPortion { address: "admin3".to_string(), portion: huge };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::WeightOverflow {}));
+        let msg = ExecuteMsg::AddMember { address: "admin4".to_string(), portion: huge };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::WeightOverflow {}));
+
+        assert_eq!(query_get_members(deps.as_ref()).unwrap(), vec![UserInfo{
+            address:"admin2".to_string(),
+            portion:Decimal::one()
+        },UserInfo{
+            address:"admin3".to_string(),
+            portion:Decimal::percent(200)
+        }]);
+
+        // weights 1 and 2 split a fee of 10 into 3 and 6 with 1 left over
+        let payouts = compute_payouts(
+            Uint128::new(100),
+            Decimal::percent(10),
+            None,
+            &query_get_members(deps.as_ref()).unwrap(),
+            "seller",
+            "dust",
+        );
+        let amounts: Vec<u128> = payouts.iter().map(|p| p.amount.u128()).collect();
+        assert_eq!(amounts, vec![90, 3, 6, 1]);
+    }
+
+    #[test]
+    fn fee_terms_frozen_at_list_time() {
+        let mut deps = mock_dependencies_with_nfts(&[]);
+        deps.querier.set_default_nft_owner(MOCK_CONTRACT_ADDR);
+        let instantiate_msg = InstantiateMsg {
+            royalty_portion:Decimal::percent(2),
+            remainder_address:None,
+            max_royalty_portion:None,
+            treasury:Some("treasury".to_string()),
+            timelock_delay:None
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg).unwrap();
+        accept_denom(deps.as_mut(), "ujuno");
+
+        let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg{
+            sender:"seller".to_string(),
+            token_id:"Hope.1".to_string(),
+            msg:to_binary(&SellNft{
+                list_price:Asset{
+                    denom:"ujuno".to_string(),
+                    amount:Uint128::new(100)
+                },
+                reserved_for:None,
+                alternative_prices:None,
+                storefront:None,
+                pull_proceeds:None
+            }).unwrap()
+        });
+        execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), msg).unwrap();
+
+        let msg = ExecuteMsg::ChangeRoyaltyPortion { royalty_portion: Decimal::percent(50) };
+        execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+        let offerings = query_get_offerings(deps.as_ref(), None, None).unwrap().offerings;
+        assert_eq!(offerings[0].royalty_portion, Decimal::percent(2));
+
+        let info = mock_info("buyer", &[Coin{
+            denom:"ujuno".to_string(),
+            amount:Uint128::new(100)
+        }]);
+        let msg = ExecuteMsg::BuyNft { offering_id: "1".to_string(), recipient: None, msg: None, referrer: None };
+        let res = execute_checked(&mut deps, mock_env(), info, msg).unwrap();
+        assert_eq!(res.messages[1].msg,CosmosMsg::Bank(BankMsg::Send {
+                to_address: "seller".to_string(),
+                amount:vec![Coin{
+                    denom:"ujuno".to_string(),
+                    amount:Uint128::new(98)
+                }]
+        }));
+        assert_eq!(res.messages[2].msg,CosmosMsg::Bank(BankMsg::Send {
+                to_address: "treasury".to_string(),
+                amount:vec![Coin{
+                    denom:"ujuno".to_string(),
+                    amount:Uint128::new(2)
+                }]
+        }));
+    }
+
+    #[test]
+    fn config_changes_wait_for_timelock() {
+        let mut deps = mock_dependencies_with_nfts(&[]);
+        let instantiate_msg = InstantiateMsg {
+            royalty_portion:Decimal::percent(2),
+            remainder_address:None,
+            max_royalty_portion:Some(Decimal::percent(10)),
+            treasury:None,
+            timelock_delay:Some(3600)
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg).unwrap();
+        accept_denom(deps.as_mut(), "ujuno");
+
+        // invalid changes are rejected when they are queued
+        let msg = ExecuteMsg::ChangeRoyaltyPortion { royalty_portion: Decimal::percent(20) };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::RoyaltyTooHigh { .. }));
+
+        // a delay given in milliseconds would lock the config for years
+        let msg = ExecuteMsg::SetTimelockDelay { delay: 86_400_000 };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::TimelockTooLong { delay: 86_400_000, .. }));
+
+        let msg = ExecuteMsg::ChangeRoyaltyPortion { royalty_portion: Decimal::percent(5) };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("anyone", &[]), msg.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        let res = execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+        assert_eq!(res.events[0].ty, "config_change_queued".to_string());
+        let msg = ExecuteMsg::SetNftAddress { address: "nft_address1".to_string() };
+        execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+        let state = query_state_info(deps.as_ref()).unwrap();
+        assert_eq!(state.royalty_portion, Decimal::percent(2));
+        assert_eq!(state.nft_address, "nft_address".to_string());
+
+        let executable_at = mock_env().block.time.seconds() + 3600;
+        assert_eq!(query_pending_changes(deps.as_ref()).unwrap().changes, vec![
+            QueryPendingChangeResult {
+                change_id: "1".to_string(),
+                change: ConfigChange::RoyaltyPortion { royalty_portion: Decimal::percent(5) },
+                executable_at,
+            },
+            QueryPendingChangeResult {
+                change_id: "2".to_string(),
+                change: ConfigChange::NftAddress { address: "nft_address1".to_string() },
+                executable_at,
+            },
+        ]);
+
+        let msg = ExecuteMsg::ApplyPendingChange { change_id: "1".to_string() };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::ChangeNotReady { .. }));
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(3600);
+        execute_checked(&mut deps, env.clone(), mock_info("owner", &[]), msg.clone()).unwrap();
+        assert_eq!(query_state_info(deps.as_ref()).unwrap().royalty_portion, Decimal::percent(5));
+        execute_checked(&mut deps, env.clone(), mock_info("owner", &[]), msg).unwrap_err();
+
+        let msg = ExecuteMsg::CancelPendingChange { change_id: "2".to_string() };
+        execute_checked(&mut deps, env.clone(), mock_info("owner", &[]), msg).unwrap();
+        assert_eq!(query_pending_changes(deps.as_ref()).unwrap().changes, vec![]);
+        assert_eq!(query_state_info(deps.as_ref()).unwrap().nft_address, "nft_address".to_string());
+
+        // redirecting fee money waits just the same
+        let msg = ExecuteMsg::SetTreasury { address: "treasury1".to_string() };
+        execute_checked(&mut deps, env.clone(), mock_info("owner", &[]), msg).unwrap();
+        let msg = ExecuteMsg::SetRemainderAddress { address: "dust1".to_string() };
+        execute_checked(&mut deps, env.clone(), mock_info("owner", &[]), msg).unwrap();
+        let state = query_state_info(deps.as_ref()).unwrap();
+        assert_eq!((state.treasury, state.remainder_address), ("owner".to_string(), "owner".to_string()));
+        env.block.time = env.block.time.plus_seconds(3600);
+        for change_id in vec!["3", "4"] {
+            let msg = ExecuteMsg::ApplyPendingChange { change_id: change_id.to_string() };
+            execute_checked(&mut deps, env.clone(), mock_info("owner", &[]), msg).unwrap();
+        }
+        let state = query_state_info(deps.as_ref()).unwrap();
+        assert_eq!((state.treasury, state.remainder_address), ("treasury1".to_string(), "dust1".to_string()));
+    }
+
+    #[test]
+    fn volume_tiers_and_hope_discount() {
+        let mut deps = mock_dependencies_with_nfts(&[]);
+        deps.querier.set_default_nft_owner(MOCK_CONTRACT_ADDR);
+        let instantiate_msg = InstantiateMsg {
+            royalty_portion:Decimal::percent(4),
+            remainder_address:None,
+            max_royalty_portion:Some(Decimal::percent(10)),
+            treasury:Some("treasury".to_string()),
+            timelock_delay:None
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg).unwrap();
+        accept_denom(deps.as_mut(), "ujuno");
+
+        let msg = ExecuteMsg::SetFeeTiers { denom: "ujuno".to_string(), tiers: vec![
+            FeeTier { min_volume: Uint128::new(1000), royalty_portion: Decimal::percent(2) },
+            FeeTier { min_volume: Uint128::new(100), royalty_portion: Decimal::percent(3) },
+        ] };
+        execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+        let msg = ExecuteMsg::SetFeeTiers { denom: "ujuno".to_string(), tiers: vec![
+            FeeTier { min_volume: Uint128::new(1000), royalty_portion: Decimal::percent(20) },
+        ] };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::RoyaltyTooHigh { .. }));
+        let msg = ExecuteMsg::SetHopeDiscount { hope_discount: Decimal::percent(50) };
+        execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+        let msg = ExecuteMsg::SetVolumeWindow { window: 86400 };
+        execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+        let fee = query_fee_for(deps.as_ref(), mock_env(), "buyer".to_string(), "ujuno".to_string()).unwrap();
+        assert_eq!(fee, FeeForResponse { royalty_portion: Decimal::percent(4), volume: Uint128::zero() });
+        let fee = query_fee_for(deps.as_ref(), mock_env(), "buyer".to_string(), "hope".to_string()).unwrap();
+        assert_eq!(fee.royalty_portion, Decimal::percent(2));
+
+        for (token_id, price) in vec![("Hope.1", 1000u128), ("Hope.2", 1000u128)] {
+            let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg{
+                sender:"seller".to_string(),
+                token_id:token_id.to_string(),
+                msg:to_binary(&SellNft{
+                    list_price:Asset{
+                        denom:"ujuno".to_string(),
+                        amount:Uint128::new(price)
+                    },
+                    reserved_for:None,
+                    alternative_prices:None,
+                    storefront:None,
+                    pull_proceeds:None
+                }).unwrap()
+            });
+            execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), msg).unwrap();
+        }
+
+        // the first purchase pays the base fee and reaches the top tier
+        let info = mock_info("buyer", &[Coin{
+            denom:"ujuno".to_string(),
+            amount:Uint128::new(1000)
+        }]);
+        let msg = ExecuteMsg::BuyNft { offering_id: "1".to_string(), recipient: None, msg: None, referrer: None };
+        let res = execute_checked(&mut deps, mock_env(), info.clone(), msg).unwrap();
+        assert_eq!(res.messages[2].msg,CosmosMsg::Bank(BankMsg::Send {
+                to_address: "treasury".to_string(),
+                amount:vec![Coin{
+                    denom:"ujuno".to_string(),
+                    amount:Uint128::new(40)
+                }]
+        }));
+        confirm_submessages(&mut deps, &res);
+
+        let fee = query_fee_for(deps.as_ref(), mock_env(), "buyer".to_string(), "ujuno".to_string()).unwrap();
+        assert_eq!(fee, FeeForResponse { royalty_portion: Decimal::percent(2), volume: Uint128::new(1000) });
+
+        let msg = ExecuteMsg::BuyNft { offering_id: "2".to_string(), recipient: None, msg: None, referrer: None };
+        let res = execute_checked(&mut deps, mock_env(), info, msg).unwrap();
+        assert_eq!(res.messages[2].msg,CosmosMsg::Bank(BankMsg::Send {
+                to_address: "treasury".to_string(),
+                amount:vec![Coin{
+                    denom:"ujuno".to_string(),
+                    amount:Uint128::new(20)
+                }]
+        }));
+        confirm_submessages(&mut deps, &res);
+
+        // volume rolls out of the window after two windows
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(86400);
+        let fee = query_fee_for(deps.as_ref(), env.clone(), "buyer".to_string(), "ujuno".to_string()).unwrap();
+        assert_eq!(fee.volume, Uint128::new(2000));
+        env.block.time = env.block.time.plus_seconds(86400);
+        let fee = query_fee_for(deps.as_ref(), env, "buyer".to_string(), "ujuno".to_string()).unwrap();
+        assert_eq!(fee, FeeForResponse { royalty_portion: Decimal::percent(4), volume: Uint128::zero() });
+    }
+
+    #[test]
+    fn buy_for_recipient_with_hook() {
+        let mut deps = mock_dependencies_with_nfts(&[]);
+        deps.querier.set_default_nft_owner(MOCK_CONTRACT_ADDR);
+        let instantiate_msg = InstantiateMsg {
+            royalty_portion:Decimal::percent(2),
+            remainder_address:None,
+            max_royalty_portion:None,
+            treasury:None,
+            timelock_delay:None
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg).unwrap();
+        accept_denom(deps.as_mut(), "ujuno");
+        let msg = ExecuteMsg::SetTokenAddress { address: "hope_token".to_string() };
+        execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+        for (token_id, denom) in vec![("Hope.1", "ujuno"), ("Hope.2", "hope")] {
+            let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg{
+                sender:"seller".to_string(),
+                token_id:token_id.to_string(),
+                msg:to_binary(&SellNft{
+                    list_price:Asset{
+                        denom:denom.to_string(),
+                        amount:Uint128::new(100)
+                    },
+                    reserved_for:None,
+                    alternative_prices:None,
+                    storefront:None,
+                    pull_proceeds:None
+                }).unwrap()
+            });
+            execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), msg).unwrap();
+        }
+
+        let info = mock_info("buyer", &[Coin{
+            denom:"ujuno".to_string(),
+            amount:Uint128::new(100)
+        }]);
+        let msg = ExecuteMsg::BuyNft {
+            offering_id: "1".to_string(),
+            recipient: Some("friend".to_string()),
+            msg: None,
+            referrer: None,
+        };
+        let res = execute_checked(&mut deps, mock_env(), info, msg).unwrap();
+        assert_eq!(res.messages[0].msg, CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "nft_address".to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw721ExecuteMsg::TransferNft {
+                    recipient: "friend".to_string(),
+                    token_id: "Hope.1".to_string(),
+            }).unwrap(),
+        }));
+
+        let hook = to_binary(&"deposit").unwrap();
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg{
+            sender:"buyer".to_string(),
+            amount:Uint128::new(100),
+            msg:to_binary(&BuyNft{
+                offering_id:"2".to_string(),
+                recipient:Some("vault".to_string()),
+                msg:Some(hook.clone()),
+                referrer:None
+            }).unwrap()
+        });
+        let res = execute_checked(&mut deps, mock_env(), mock_info("hope_token", &[]), msg).unwrap();
+        assert_eq!(res.messages[0].msg, CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "nft_address".to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw721ExecuteMsg::SendNft {
+                    contract: "vault".to_string(),
+                    token_id: "Hope.2".to_string(),
+                    msg: hook,
+            }).unwrap(),
+        }));
+    }
+
+    #[test]
+    fn approval_listings() {
+        let mut deps = mock_dependencies_with_nfts(&[]);
+        deps.querier.set_default_nft_owner(MOCK_CONTRACT_ADDR);
+        let instantiate_msg = InstantiateMsg {
+            royalty_portion:Decimal::percent(2),
+            remainder_address:None,
+            max_royalty_portion:None,
+            treasury:Some("treasury".to_string()),
+            timelock_delay:None
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg).unwrap();
+        accept_denom(deps.as_mut(), "ujuno");
+        let list_price = Asset{
+            denom:"ujuno".to_string(),
+            amount:Uint128::new(100)
+        };
+
+        // listing needs both ownership and an approval for the marketplace
+        deps.querier.set_nft_owner("nft_address", "Hope.1", "seller", &[]);
+        let msg = ExecuteMsg::ListWithApproval { nft_address: None, token_id: "Hope.1".to_string(), list_price: list_price.clone(), storefront: None };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("seller", &[]), msg.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::ApprovalMissing {}));
+        deps.querier.set_nft_owner("nft_address", "Hope.1", "seller", &[MOCK_CONTRACT_ADDR]);
+        let err = execute_checked(&mut deps, mock_env(), mock_info("thief", &[]), msg.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::ApprovalMissing {}));
+        let res = execute_checked(&mut deps, mock_env(), mock_info("seller", &[]), msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        // custodial and approval listings live side by side
+        let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg{
+            sender:"seller".to_string(),
+            token_id:"Hope.2".to_string(),
+            msg:to_binary(&SellNft{ list_price:list_price.clone(), reserved_for:None, alternative_prices:None, storefront:None, pull_proceeds:None }).unwrap()
+        });
+        execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), msg).unwrap();
+        deps.querier.set_nft_owner("nft_address", "Hope.3", "seller", &[MOCK_CONTRACT_ADDR]);
+        let msg = ExecuteMsg::ListWithApproval { nft_address: None, token_id: "Hope.3".to_string(), list_price: list_price.clone(), storefront: None };
+        execute_checked(&mut deps, mock_env(), mock_info("seller", &[]), msg).unwrap();
+        let custody: Vec<Custody> = query_get_offerings(deps.as_ref(), None, None).unwrap().offerings
+            .into_iter().map(|o| o.custody).collect();
+        assert_eq!(custody, vec![Custody::Approval, Custody::Escrow, Custody::Approval]);
+
+        let funds = vec![Coin{
+            denom:"ujuno".to_string(),
+            amount:Uint128::new(100)
+        }];
+        let msg = ExecuteMsg::BuyNft { offering_id: "1".to_string(), recipient: None, msg: None, referrer: None };
+        let res = execute_checked(&mut deps, mock_env(), mock_info("buyer", &funds), msg).unwrap();
+        assert_eq!(res.messages[0].msg, CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "nft_address".to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw721ExecuteMsg::TransferNft {
+                    recipient: "buyer".to_string(),
+                    token_id: "Hope.1".to_string(),
+            }).unwrap(),
+        }));
+
+        // the seller moved the token, so the listing is stale and the buyer is refunded
+        deps.querier.set_nft_owner("nft_address", "Hope.3", "someone", &[]);
+        let msg = ExecuteMsg::BuyNft { offering_id: "3".to_string(), recipient: None, msg: None, referrer: None };
+        let res = execute_checked(&mut deps, mock_env(), mock_info("buyer", &funds), msg).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+            to_address: "buyer".to_string(),
+            amount: funds,
+        }));
+        let ids: Vec<String> = query_get_offerings(deps.as_ref(), None, None).unwrap().offerings
+            .into_iter().map(|o| o.id).collect();
+        assert_eq!(ids, vec!["2".to_string()]);
+    }
+
+    #[test]
+    fn batch_approval_listing() {
+        let mut deps = mock_dependencies_with_nfts(&[]);
+        let instantiate_msg = InstantiateMsg {
+            royalty_portion:Decimal::percent(2),
+            remainder_address:None,
+            max_royalty_portion:None,
+            treasury:None,
+            timelock_delay:None
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg).unwrap();
+        accept_denom(deps.as_mut(), "ujuno");
+        for token_id in vec!["Hope.1", "Hope.2", "Hope.3"] {
+            deps.querier.set_nft_owner("nft_address", token_id, "seller", &[MOCK_CONTRACT_ADDR]);
+        }
+        let uniform = Asset{
+            denom:"ujuno".to_string(),
+            amount:Uint128::new(100)
+        };
+        let special = Asset{
+            denom:"hope".to_string(),
+            amount:Uint128::new(500)
+        };
+
+        let msg = ExecuteMsg::ListManyWithApproval {
+            nft_address: None,
+            tokens: vec![
+                ApprovalListing { token_id: "Hope.1".to_string(), list_price: None },
+                ApprovalListing { token_id: "Hope.2".to_string(), list_price: None },
+            ],
+            list_price: None,
+            storefront: None,
+        };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("seller", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::MissingPrice { .. }));
+
+        let msg = ExecuteMsg::ListManyWithApproval {
+            nft_address: None,
+            tokens: vec![
+                ApprovalListing { token_id: "Hope.1".to_string(), list_price: None },
+                ApprovalListing { token_id: "Hope.1".to_string(), list_price: None },
+            ],
+            list_price: Some(uniform.clone()),
+            storefront: None,
+        };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("seller", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::DuplicateToken { .. }));
+
+        let msg = ExecuteMsg::ListManyWithApproval {
+            nft_address: None,
+            tokens: vec![
+                ApprovalListing { token_id: "Hope.1".to_string(), list_price: None },
+                ApprovalListing { token_id: "Hope.2".to_string(), list_price: Some(special.clone()) },
+                ApprovalListing { token_id: "Hope.3".to_string(), list_price: None },
+            ],
+            list_price: Some(uniform.clone()),
+            storefront: None,
+        };
+        let res = execute_checked(&mut deps, mock_env(), mock_info("seller", &[]), msg).unwrap();
+        assert_eq!(res.events.len(), 3);
+        assert_eq!(res.events[1].attributes[0].value, "2".to_string());
+
+        let prices: Vec<(String, Asset)> = query_get_offerings(deps.as_ref(), None, None).unwrap().offerings
+            .into_iter().map(|o| (o.id, o.list_price)).collect();
+        assert_eq!(prices, vec![
+            ("1".to_string(), uniform.clone()),
+            ("2".to_string(), special),
+            ("3".to_string(), uniform),
+        ]);
+    }
+
+    #[test]
+    fn buy_many_offerings() {
+        let mut deps = mock_dependencies_with_nfts(&[]);
+        deps.querier.set_default_nft_owner(MOCK_CONTRACT_ADDR);
+        let instantiate_msg = InstantiateMsg {
+            royalty_portion:Decimal::percent(10),
+            remainder_address:None,
+            max_royalty_portion:None,
+            treasury:Some("treasury".to_string()),
+            timelock_delay:None
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg).unwrap();
+        accept_denom(deps.as_mut(), "ujuno");
+        let msg = ExecuteMsg::SetTokenAddress { address: "hope_token".to_string() };
+        execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+        for (token_id, denom, price) in vec![("Hope.1", "ujuno", 100u128), ("Hope.2", "ujuno", 200u128),
+            ("Hope.3", "hope", 300u128), ("Hope.4", "hope", 400u128)] {
+            let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg{
+                sender:"seller".to_string(),
+                token_id:token_id.to_string(),
+                msg:to_binary(&SellNft{
+                    list_price:Asset{
+                        denom:denom.to_string(),
+                        amount:Uint128::new(price)
+                    },
+                    reserved_for:None,
+                    alternative_prices:None,
+                    storefront:None,
+                    pull_proceeds:None
+                }).unwrap()
+            });
+            execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), msg).unwrap();
+        }
+        let funds = vec![Coin{
+            denom:"ujuno".to_string(),
+            amount:Uint128::new(350)
+        }];
+
+        // offering 3 is priced in hope, so the strict cart fails as a whole
+        let msg = ExecuteMsg::BuyMany {
+            offering_ids: vec!["1".to_string(), "2".to_string(), "3".to_string()],
+            max_total: Uint128::new(1000),
+            skip_unavailable: false,
+        };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("buyer", &funds), msg).unwrap_err();
+        assert!(matches!(err, ContractError::OfferingUnavailable { .. }));
+
+        let msg = ExecuteMsg::BuyMany {
+            offering_ids: vec!["1".to_string(), "2".to_string(), "3".to_string()],
+            max_total: Uint128::new(250),
+            skip_unavailable: true,
+        };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("buyer", &funds), msg).unwrap_err();
+        assert!(matches!(err, ContractError::MaxTotalExceeded { .. }));
+
+        let msg = ExecuteMsg::BuyMany {
+            offering_ids: vec!["1".to_string(), "2".to_string(), "3".to_string()],
+            max_total: Uint128::new(300),
+            skip_unavailable: true,
+        };
+        let res = execute_checked(&mut deps, mock_env(), mock_info("buyer", &funds), msg).unwrap();
+        // nft, seller and fee for each offering, then the refund
+        assert_eq!(res.messages.len(), 7);
+        assert_eq!(res.messages[2].msg, CosmosMsg::Bank(BankMsg::Send {
+            to_address: "treasury".to_string(),
+            amount: vec![Coin{ denom:"ujuno".to_string(), amount:Uint128::new(10) }],
+        }));
+        assert_eq!(res.messages[5].msg, CosmosMsg::Bank(BankMsg::Send {
+            to_address: "treasury".to_string(),
+            amount: vec![Coin{ denom:"ujuno".to_string(), amount:Uint128::new(20) }],
+        }));
+        assert_eq!(res.messages[6].msg, CosmosMsg::Bank(BankMsg::Send {
+            to_address: "buyer".to_string(),
+            amount: vec![Coin{ denom:"ujuno".to_string(), amount:Uint128::new(50) }],
+        }));
+
+        // the same cart through the cw20 hook
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg{
+            sender:"buyer".to_string(),
+            amount:Uint128::new(600),
+            msg:to_binary(&Cw20HookMsg::BuyMany(BuyMany{
+                offering_ids: vec!["3".to_string(), "4".to_string()],
+                max_total: Uint128::new(700),
+                skip_unavailable: false,
+            })).unwrap()
+        });
+        let err = execute_checked(&mut deps, mock_env(), mock_info("hope_token", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::NotEnoughFunds {}));
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg{
+            sender:"buyer".to_string(),
+            amount:Uint128::new(700),
+            msg:to_binary(&Cw20HookMsg::BuyMany(BuyMany{
+                offering_ids: vec!["3".to_string(), "4".to_string()],
+                max_total: Uint128::new(700),
+                skip_unavailable: false,
+            })).unwrap()
+        });
+        let res = execute_checked(&mut deps, mock_env(), mock_info("hope_token", &[]), msg).unwrap();
+        assert_eq!(res.messages.len(), 6);
+        assert_eq!(query_get_offerings(deps.as_ref(), None, None).unwrap().offerings, vec![]);
+    }
+
+    #[test]
+    fn reserved_listings() {
+        let mut deps = mock_dependencies_with_nfts(&[]);
+        deps.querier.set_default_nft_owner(MOCK_CONTRACT_ADDR);
+        let instantiate_msg = InstantiateMsg {
+            royalty_portion:Decimal::percent(2),
+            remainder_address:None,
+            max_royalty_portion:None,
+            treasury:None,
+            timelock_delay:None
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg).unwrap();
+        accept_denom(deps.as_mut(), "ujuno");
+        let list_price = Asset{
+            denom:"ujuno".to_string(),
+            amount:Uint128::new(100)
+        };
+        for (token_id, reserved_for) in vec![("Hope.1", None), ("Hope.2", Some(vec!["partner".to_string()]))] {
+            let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg{
+                sender:"seller".to_string(),
+                token_id:token_id.to_string(),
+                msg:to_binary(&SellNft{ list_price:list_price.clone(), reserved_for, alternative_prices:None, storefront:None, pull_proceeds:None }).unwrap()
+            });
+            execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), msg).unwrap();
+        }
+
+        let ids = |deps: Deps, reserved_for: Option<&str>| -> Vec<String> {
+            query_get_offerings(deps, reserved_for.map(|a| a.to_string()), None).unwrap().offerings
+                .into_iter().map(|o| o.id).collect()
+        };
+        assert_eq!(ids(deps.as_ref(), None), vec!["1".to_string()]);
+        assert_eq!(ids(deps.as_ref(), Some("stranger")), vec!["1".to_string()]);
+        assert_eq!(ids(deps.as_ref(), Some("partner")), vec!["1".to_string(), "2".to_string()]);
+
+        let funds = vec![Coin{
+            denom:"ujuno".to_string(),
+            amount:Uint128::new(100)
+        }];
+        let msg = ExecuteMsg::BuyNft { offering_id: "2".to_string(), recipient: None, msg: None, referrer: None };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("stranger", &funds), msg.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::ReservedListing {}));
+        let msg_many = ExecuteMsg::BuyMany {
+            offering_ids: vec!["2".to_string()],
+            max_total: Uint128::new(100),
+            skip_unavailable: false,
+        };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("stranger", &funds), msg_many).unwrap_err();
+        assert!(matches!(err, ContractError::OfferingUnavailable { .. }));
+        execute_checked(&mut deps, mock_env(), mock_info("partner", &funds), msg).unwrap();
+    }
+
+    #[test]
+    fn bundle_offerings() {
+        let mut deps = mock_dependencies_with_nfts(&[]);
+        deps.querier.set_default_nft_owner(MOCK_CONTRACT_ADDR);
+        let instantiate_msg = InstantiateMsg {
+            royalty_portion:Decimal::percent(10),
+            remainder_address:None,
+            max_royalty_portion:None,
+            treasury:Some("treasury".to_string()),
+            timelock_delay:None
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg).unwrap();
+        accept_denom(deps.as_mut(), "ujuno");
+        let hero = NftItem { nft_address: "nft_address".to_string(), token_id: "Hero.1".to_string() };
+        let sword = NftItem { nft_address: "items".to_string(), token_id: "Sword.1".to_string() };
+        let list_price = Asset{
+            denom:"ujuno".to_string(),
+            amount:Uint128::new(1000)
+        };
+
+        let msg = ExecuteMsg::CreateBundle {
+            items: vec![hero.clone(), sword.clone()],
+            list_price: list_price.clone(),
+            reserved_for: None,
+        };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("seller", &[]), msg.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::WrongNFTContractError {}));
+        let register = ExecuteMsg::RegisterCollection { address: "items".to_string() };
+        execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), register).unwrap();
+        execute_checked(&mut deps, mock_env(), mock_info("seller", &[]), msg).unwrap();
+
+        let deposit = |token_id: &str, sender: &str| ExecuteMsg::ReceiveNft(Cw721ReceiveMsg{
+            sender:sender.to_string(),
+            token_id:token_id.to_string(),
+            msg:to_binary(&Cw721HookMsg::DepositBundle { bundle_id: "1".to_string() }).unwrap()
+        });
+        let err = execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), deposit("Hero.1", "thief")).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        let err = execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), deposit("Hero.2", "seller")).unwrap_err();
+        assert!(matches!(err, ContractError::NotInBundle { .. }));
+        execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), deposit("Hero.1", "seller")).unwrap();
+        let err = execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), deposit("Hero.1", "seller")).unwrap_err();
+        assert!(matches!(err, ContractError::NotInBundle { .. }));
+
+        // nothing is listed until every piece is in escrow
+        assert_eq!(query_get_offerings(deps.as_ref(), None, None).unwrap().offerings, vec![]);
+        execute_checked(&mut deps, mock_env(), mock_info("items", &[]), deposit("Sword.1", "seller")).unwrap();
+        let offerings = query_get_offerings(deps.as_ref(), None, None).unwrap().offerings;
+        assert_eq!(offerings[0].bundle, vec![hero.clone(), sword.clone()]);
+
+        let funds = vec![Coin{
+            denom:"ujuno".to_string(),
+            amount:Uint128::new(1000)
+        }];
+        let msg = ExecuteMsg::BuyNft { offering_id: "1".to_string(), recipient: None, msg: None, referrer: None };
+        let res = execute_checked(&mut deps, mock_env(), mock_info("buyer", &funds), msg).unwrap();
+        assert_eq!(res.messages.len(), 4);
+        assert_eq!(res.messages[1].msg, CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "items".to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw721ExecuteMsg::TransferNft {
+                    recipient: "buyer".to_string(),
+                    token_id: "Sword.1".to_string(),
+            }).unwrap(),
+        }));
+
+        // an unfinished bundle hands back what was deposited
+        let msg = ExecuteMsg::CreateBundle {
+            items: vec![hero, sword],
+            list_price,
+            reserved_for: None,
+        };
+        execute_checked(&mut deps, mock_env(), mock_info("seller", &[]), msg).unwrap();
+        deps.querier.set_nft_owner("items", "Sword.1", MOCK_CONTRACT_ADDR, &[]);
+        let deposit = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg{
+            sender:"seller".to_string(),
+            token_id:"Sword.1".to_string(),
+            msg:to_binary(&Cw721HookMsg::DepositBundle { bundle_id: "2".to_string() }).unwrap()
+        });
+        execute_checked(&mut deps, mock_env(), mock_info("items", &[]), deposit).unwrap();
+        let msg = ExecuteMsg::CancelBundle { bundle_id: "2".to_string() };
+        let res = execute_checked(&mut deps, mock_env(), mock_info("seller", &[]), msg).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(res.messages[0].msg, CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "items".to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw721ExecuteMsg::TransferNft {
+                    recipient: "seller".to_string(),
+                    token_id: "Sword.1".to_string(),
+            }).unwrap(),
+        }));
+    }
+
+    #[test]
+    fn nft_swaps() {
+        let mut deps = mock_dependencies_with_nfts(&[]);
+        deps.querier.set_default_nft_owner(MOCK_CONTRACT_ADDR);
+        let instantiate_msg = InstantiateMsg {
+            royalty_portion:Decimal::percent(10),
+            remainder_address:None,
+            max_royalty_portion:None,
+            treasury:None,
+            timelock_delay:None
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg).unwrap();
+        accept_denom(deps.as_mut(), "ujuno");
+        let item = |token_id: &str| NftItem { nft_address: "nft_address".to_string(), token_id: token_id.to_string() };
+        let transfer = |recipient: &str, token_id: &str| CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "nft_address".to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw721ExecuteMsg::TransferNft {
+                    recipient: recipient.to_string(),
+                    token_id: token_id.to_string(),
+            }).unwrap(),
+        });
+        let hook = |sender: &str, token_id: &str, msg: Cw721HookMsg| ExecuteMsg::ReceiveNft(Cw721ReceiveMsg{
+            sender:sender.to_string(),
+            token_id:token_id.to_string(),
+            msg:to_binary(&msg).unwrap()
+        });
+
+        let proposal = ProposeSwap {
+            offered: vec![item("Hero.1"), item("Hero.2")],
+            wanted: item("Hero.3"),
+            end_height: None,
+            end_time: None,
+        };
+        let funds = vec![Coin{
+            denom:"ujuno".to_string(),
+            amount:Uint128::new(50)
+        }];
+        execute_checked(&mut deps, mock_env(), mock_info("alice", &funds), ExecuteMsg::ProposeSwap(proposal)).unwrap();
+        let deposit = || Cw721HookMsg::DepositSwap { swap_id: "1".to_string() };
+        let accept = || Cw721HookMsg::AcceptSwap { swap_id: "1".to_string() };
+        execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), hook("alice", "Hero.1", deposit())).unwrap();
+
+        let err = execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), hook("bob", "Hero.3", accept())).unwrap_err();
+        assert!(matches!(err, ContractError::SwapNotFunded {}));
+        execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), hook("alice", "Hero.2", deposit())).unwrap();
+        let err = execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), hook("bob", "Hero.4", accept())).unwrap_err();
+        assert!(matches!(err, ContractError::WrongNFTContractError {}));
+
+        let res = execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), hook("bob", "Hero.3", accept())).unwrap();
+        let messages: Vec<CosmosMsg> = res.messages.into_iter().map(|sub| sub.msg).collect();
+        assert_eq!(messages, vec![
+            transfer("alice", "Hero.3"),
+            transfer("bob", "Hero.1"),
+            transfer("bob", "Hero.2"),
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "bob".to_string(),
+                amount: funds,
+            }),
+        ]);
+        assert!(SWAPS.may_load(&deps.storage, "1").unwrap().is_none());
+
+        // a HOPE sweetener comes back when the expired proposal is cleared
+        let proposal = ProposeSwap {
+            offered: vec![item("Hero.1")],
+            wanted: item("Hero.5"),
+            end_height: Some(mock_env().block.height + 10),
+            end_time: None,
+        };
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg{
+            sender:"bob".to_string(),
+            amount:Uint128::new(70),
+            msg:to_binary(&Cw20HookMsg::ProposeSwap(proposal)).unwrap()
+        });
+        execute_checked(&mut deps, mock_env(), mock_info("token_address", &[]), msg).unwrap();
+        let deposit = Cw721HookMsg::DepositSwap { swap_id: "2".to_string() };
+        deps.querier.set_nft_owner("nft_address", "Hero.1", MOCK_CONTRACT_ADDR, &[]);
+        execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), hook("bob", "Hero.1", deposit)).unwrap();
+
+        let cancel = ExecuteMsg::CancelSwap { swap_id: "2".to_string() };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("carol", &[]), cancel.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        let mut env = mock_env();
+        env.block.height += 10;
+        let accept = Cw721HookMsg::AcceptSwap { swap_id: "2".to_string() };
+        let err = execute_checked(&mut deps, env.clone(), mock_info("nft_address", &[]), hook("alice", "Hero.5", accept)).unwrap_err();
+        assert!(matches!(err, ContractError::Expired { .. }));
+
+        let res = execute_checked(&mut deps, env, mock_info("carol", &[]), cancel).unwrap();
+        let messages: Vec<CosmosMsg> = res.messages.into_iter().map(|sub| sub.msg).collect();
+        assert_eq!(messages, vec![
+            transfer("bob", "Hero.1"),
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "token_address".to_string(),
+                funds: vec![],
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "bob".to_string(),
+                    amount: Uint128::new(70),
+                }).unwrap(),
+            }),
+        ]);
+    }
+
+    #[test]
+    fn alternative_prices() {
+        let mut deps = mock_dependencies_with_nfts(&[]);
+        deps.querier.set_default_nft_owner(MOCK_CONTRACT_ADDR);
+        let instantiate_msg = InstantiateMsg {
+            royalty_portion:Decimal::percent(10),
+            remainder_address:None,
+            max_royalty_portion:None,
+            treasury:Some("treasury".to_string()),
+            timelock_delay:None
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg).unwrap();
+        accept_denom(deps.as_mut(), "ujuno");
+        let hope = Asset{
+            denom:HOPE_DENOM.to_string(),
+            amount:Uint128::new(500)
+        };
+        let juno = Asset{
+            denom:"ujuno".to_string(),
+            amount:Uint128::new(20)
+        };
+        let sell = |token_id: &str, alternative_prices: Vec<Asset>| ExecuteMsg::ReceiveNft(Cw721ReceiveMsg{
+            sender:"seller".to_string(),
+            token_id:token_id.to_string(),
+            msg:to_binary(&SellNft{
+                list_price:hope.clone(),
+                reserved_for:None,
+                alternative_prices:Some(alternative_prices),
+                storefront:None,
+                pull_proceeds:None
+            }).unwrap()
+        });
+
+        let err = execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), sell("Hero.1", vec![juno.clone(), juno.clone()])).unwrap_err();
+        assert!(matches!(err, ContractError::DuplicatePrice { .. }));
+        let err = execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), sell("Hero.1", vec![hope.clone()])).unwrap_err();
+        assert!(matches!(err, ContractError::DuplicatePrice { .. }));
+        execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), sell("Hero.1", vec![juno.clone()])).unwrap();
+        execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), sell("Hero.2", vec![juno])).unwrap();
+
+        let buy = ExecuteMsg::BuyNft { offering_id: "1".to_string(), recipient: None, msg: None, referrer: None };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("buyer", &[Coin::new(20, "uatom")]), buy.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::NotEnoughFunds {}));
+        let both = [Coin::new(20, "ujuno"), Coin::new(20, "uatom")];
+        let err = execute_checked(&mut deps, mock_env(), mock_info("buyer", &both), buy.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidFunds {}));
+        let res = execute_checked(&mut deps, mock_env(), mock_info("buyer", &[Coin::new(20, "ujuno")]), buy).unwrap();
+        let payouts: Vec<CosmosMsg> = res.messages.into_iter().skip(1).map(|sub| sub.msg).collect();
+        assert_eq!(payouts, vec![
+            CosmosMsg::Bank(BankMsg::Send { to_address: "seller".to_string(), amount: vec![Coin::new(18, "ujuno")] }),
+            CosmosMsg::Bank(BankMsg::Send { to_address: "treasury".to_string(), amount: vec![Coin::new(2, "ujuno")] }),
+        ]);
+
+        // a native coin named like HOPE is no HOPE payment
+        let native_hope = [Coin::new(500, HOPE_DENOM)];
+        let item = |token_id: &str| NftItem { nft_address: "nft_address".to_string(), token_id: token_id.to_string() };
+        let attempts = vec![
+            ExecuteMsg::BuyNft { offering_id: "2".to_string(), recipient: None, msg: None, referrer: None },
+            ExecuteMsg::BuyMany { offering_ids: vec!["2".to_string()], max_total: Uint128::new(500), skip_unavailable: false },
+            ExecuteMsg::ProposeSwap(ProposeSwap { offered: vec![item("Hope.9")], wanted: item("Hope.2"), end_height: None, end_time: None }),
+            ExecuteMsg::SubmitCollection(SubmitCollection {
+                address: "items".to_string(),
+                name: "Items".to_string(),
+                description: "A collection".to_string(),
+            }),
+        ];
+        for msg in attempts {
+            let err = execute_checked(&mut deps, mock_env(), mock_info("buyer", &native_hope), msg).unwrap_err();
+            assert!(matches!(err, ContractError::NativeHope {}));
+        }
+
+        // the same kind of listing settles in HOPE when paid through the token
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg{
+            sender:"buyer".to_string(),
+            amount:Uint128::new(500),
+            msg:to_binary(&BuyNft{ offering_id:"2".to_string(), recipient:None, msg:None, referrer:None }).unwrap()
+        });
+        let res = execute_checked(&mut deps, mock_env(), mock_info("token_address", &[]), msg).unwrap();
+        let payouts: Vec<CosmosMsg> = res.messages.into_iter().skip(1).map(|sub| sub.msg).collect();
+        assert_eq!(payouts[0], CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "token_address".to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "seller".to_string(),
+                amount: Uint128::new(450),
+            }).unwrap(),
+        }));
+    }
+
+    #[test]
+    fn denom_registry() {
+        let mut deps = mock_dependencies_with_nfts(&[]);
+        deps.querier.set_default_nft_owner(MOCK_CONTRACT_ADDR);
+        let instantiate_msg = InstantiateMsg {
+            royalty_portion:Decimal::percent(10),
+            remainder_address:None,
+            max_royalty_portion:None,
+            treasury:None,
+            timelock_delay:None
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg).unwrap();
+        let sell = |token_id: &str, denom: &str, amount: u128| ExecuteMsg::ReceiveNft(Cw721ReceiveMsg{
+            sender:"seller".to_string(),
+            token_id:token_id.to_string(),
+            msg:to_binary(&SellNft{
+                list_price:Asset{ denom:denom.to_string(), amount:Uint128::new(amount) },
+                reserved_for:None,
+                alternative_prices:None,
+                storefront:None,
+                pull_proceeds:None
+            }).unwrap()
+        });
+
+        let err = execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), sell("Hero.1", "uatom", 100)).unwrap_err();
+        assert!(matches!(err, ContractError::UnsupportedDenom { .. }));
+
+        let register = ExecuteMsg::SetDenom {
+            denom: "uatom".to_string(),
+            symbol: "ATOM".to_string(),
+            decimals: 6,
+            min_price: Uint128::new(100),
+        };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("seller", &[]), register.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), register).unwrap();
+        let res: DenomsResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::GetDenoms {}).unwrap()).unwrap();
+        assert_eq!(res.denoms, vec![QueryDenomResult {
+            denom: "uatom".to_string(),
+            symbol: "ATOM".to_string(),
+            decimals: 6,
+            min_price: Uint128::new(100),
+        }]);
+
+        let err = execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), sell("Hero.1", "uatom", 99)).unwrap_err();
+        assert!(matches!(err, ContractError::PriceTooLow { .. }));
+        let err = execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), sell("Hero.1", HOPE_DENOM, 0)).unwrap_err();
+        assert!(matches!(err, ContractError::PriceTooLow { .. }));
+        execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), sell("Hero.1", "uatom", 100)).unwrap();
+        execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), sell("Hero.2", HOPE_DENOM, 1)).unwrap();
+
+        let remove = ExecuteMsg::RemoveDenom { denom: "uatom".to_string() };
+        execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), remove).unwrap();
+        let err = execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), sell("Hero.3", "uatom", 100)).unwrap_err();
+        assert!(matches!(err, ContractError::UnsupportedDenom { .. }));
+    }
+
+    #[test]
+    fn collection_curation() {
+        let mut deps = mock_dependencies_with_nfts(&[]);
+        deps.querier.set_default_nft_owner(MOCK_CONTRACT_ADDR);
+        let instantiate_msg = InstantiateMsg {
+            royalty_portion:Decimal::percent(10),
+            remainder_address:None,
+            max_royalty_portion:None,
+            treasury:Some("treasury".to_string()),
+            timelock_delay:None
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg).unwrap();
+        accept_denom(deps.as_mut(), "ujuno");
+        let fee = Asset{
+            denom:"ujuno".to_string(),
+            amount:Uint128::new(100)
+        };
+        let msg = ExecuteMsg::SetCollectionFee { fee: Some(fee) };
+        execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+        let msg = ExecuteMsg::SetCurators { curators: vec!["curator".to_string()] };
+        execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+        let submit = |address: &str| ExecuteMsg::SubmitCollection(SubmitCollection {
+            address: address.to_string(),
+            name: address.to_uppercase(),
+            description: "A collection".to_string(),
+        });
+        let err = execute_checked(&mut deps, mock_env(), mock_info("creator", &[]), submit("items")).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidFunds {}));
+        let fee_funds = [Coin::new(100, "ujuno")];
+        for address in vec!["items", "gadgets", "tools", "widgets"] {
+            deps.querier.set_minter(address, "creator");
+        }
+        // only the minter of a cw721 collection can submit it
+        let err = execute_checked(&mut deps, mock_env(), mock_info("stranger", &fee_funds), submit("items")).unwrap_err();
+        assert!(matches!(err, ContractError::NotCollectionMinter { .. }));
+        let err = execute_checked(&mut deps, mock_env(), mock_info("creator", &fee_funds), submit("wallet")).unwrap_err();
+        assert!(matches!(err, ContractError::Std(_)));
+        execute_checked(&mut deps, mock_env(), mock_info("creator", &fee_funds), submit("items")).unwrap();
+        execute_checked(&mut deps, mock_env(), mock_info("creator", &fee_funds), submit("gadgets")).unwrap();
+        let err = execute_checked(&mut deps, mock_env(), mock_info("creator", &fee_funds), submit("items")).unwrap_err();
+        assert!(matches!(err, ContractError::CollectionExists { .. }));
+
+        // pending collections cannot be traded yet
+        let sell = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg{
+            sender:"seller".to_string(),
+            token_id:"Sword.1".to_string(),
+            msg:to_binary(&SellNft{
+                list_price:Asset{ denom:"ujuno".to_string(), amount:Uint128::new(10) },
+                reserved_for:None,
+                alternative_prices:None,
+                storefront:None,
+                pull_proceeds:None
+            }).unwrap()
+        });
+        let err = execute_checked(&mut deps, mock_env(), mock_info("items", &[]), sell.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::WrongNFTContractError {}));
+        // and enabling trading does not skip the review
+        let msg = ExecuteMsg::SetCollectionTrading { address: "items".to_string(), trading_enabled: true };
+        execute_checked(&mut deps, mock_env(), mock_info("curator", &[]), msg).unwrap();
+        let err = execute_checked(&mut deps, mock_env(), mock_info("items", &[]), sell.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::WrongNFTContractError {}));
+
+        let review = |address: &str, approve: bool| ExecuteMsg::ReviewCollection {
+            address: address.to_string(),
+            approve,
+            verified: approve,
+        };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("creator", &[]), review("items", true)).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        let res = execute_checked(&mut deps, mock_env(), mock_info("curator", &[]), review("items", true)).unwrap();
+        assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+            to_address: "treasury".to_string(),
+            amount: fee_funds.to_vec(),
+        }));
+        let res = execute_checked(&mut deps, mock_env(), mock_info("curator", &[]), review("gadgets", false)).unwrap();
+        assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+            to_address: "creator".to_string(),
+            amount: fee_funds.to_vec(),
+        }));
+        let err = execute_checked(&mut deps, mock_env(), mock_info("curator", &[]), review("gadgets", true)).unwrap_err();
+        assert!(matches!(err, ContractError::NotPending { .. }));
+        execute_checked(&mut deps, mock_env(), mock_info("items", &[]), sell.clone()).unwrap();
+
+        let collections = query_collections(deps.as_ref(), None, None, Some(1)).unwrap().collections;
+        assert_eq!(collections.len(), 1);
+        assert_eq!(collections[0].address, "gadgets");
+        assert_eq!(collections[0].info.status, CollectionStatus::Rejected);
+        let collections = query_collections(deps.as_ref(), None, Some("gadgets".to_string()), None).unwrap().collections;
+        assert_eq!(collections[0].address, "items");
+        assert!(collections[0].info.verified);
+        let approved = query_collections(deps.as_ref(), Some(CollectionStatus::Approved), None, None).unwrap().collections;
+        assert_eq!(approved, collections);
+
+        let msg = ExecuteMsg::SetCollectionTrading { address: "items".to_string(), trading_enabled: false };
+        execute_checked(&mut deps, mock_env(), mock_info("curator", &[]), msg).unwrap();
+        let err = execute_checked(&mut deps, mock_env(), mock_info("items", &[]), sell).unwrap_err();
+        assert!(matches!(err, ContractError::WrongNFTContractError {}));
+
+        // nor can the listing made while it was enabled be bought
+        let buy = ExecuteMsg::BuyNft { offering_id: "1".to_string(), recipient: None, msg: None, referrer: None };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("buyer", &[Coin::new(10, "ujuno")]), buy).unwrap_err();
+        assert!(matches!(err, ContractError::WrongNFTContractError {}));
+        let buy_many = ExecuteMsg::BuyMany { offering_ids: vec!["1".to_string()], max_total: Uint128::new(10), skip_unavailable: false };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("buyer", &[Coin::new(10, "ujuno")]), buy_many).unwrap_err();
+        assert!(matches!(err, ContractError::OfferingUnavailable { .. }));
+
+        // registering a submission under review approves it and settles its fee
+        execute_checked(&mut deps, mock_env(), mock_info("creator", &fee_funds), submit("tools")).unwrap();
+        let msg = ExecuteMsg::RegisterCollection { address: "tools".to_string() };
+        let res = execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+        assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+            to_address: "treasury".to_string(),
+            amount: fee_funds.to_vec(),
+        }));
+        let tools = COLLECTIONS.load(&deps.storage, "tools").unwrap();
+        assert_eq!((tools.status, tools.fee_paid), (CollectionStatus::Approved, None));
+
+        // deregistering a submission under review refunds its fee
+        execute_checked(&mut deps, mock_env(), mock_info("creator", &fee_funds), submit("widgets")).unwrap();
+        let msg = ExecuteMsg::DeregisterCollection { address: "widgets".to_string() };
+        let res = execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+        assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+            to_address: "creator".to_string(),
+            amount: fee_funds.to_vec(),
+        }));
+        assert!(COLLECTIONS.may_load(&deps.storage, "widgets").unwrap().is_none());
+    }
+
+    #[test]
+    fn flagged_tokens_and_addresses() {
+        let mut deps = mock_dependencies_with_nfts(&[]);
+        deps.querier.set_default_nft_owner(MOCK_CONTRACT_ADDR);
+        let instantiate_msg = InstantiateMsg {
+            royalty_portion:Decimal::percent(10),
+            remainder_address:None,
+            max_royalty_portion:None,
+            treasury:None,
+            timelock_delay:None
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg).unwrap();
+        accept_denom(deps.as_mut(), "ujuno");
+        let msg = ExecuteMsg::SetModerators { moderators: vec!["moderator".to_string()] };
+        execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+        let sell = |token_id: &str| ExecuteMsg::ReceiveNft(Cw721ReceiveMsg{
+            sender:"seller".to_string(),
+            token_id:token_id.to_string(),
+            msg:to_binary(&SellNft{
+                list_price:Asset{ denom:"ujuno".to_string(), amount:Uint128::new(10) },
+                reserved_for:None,
+                alternative_prices:None,
+                storefront:None,
+                pull_proceeds:None
+            }).unwrap()
+        });
+        let flag_token = |token_id: &str, flagged: bool, reason: &str| ExecuteMsg::FlagToken {
+            nft_address: "nft_address".to_string(),
+            token_id: token_id.to_string(),
+            flagged,
+            reason: reason.to_string(),
+        };
+        execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), sell("Hero.1")).unwrap();
+        execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), sell("Hero.2")).unwrap();
+
+        let err = execute_checked(&mut deps, mock_env(), mock_info("seller", &[]), flag_token("Hero.1", true, "stolen")).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        execute_checked(&mut deps, mock_env(), mock_info("moderator", &[]), flag_token("Hero.1", true, "stolen")).unwrap();
+        execute_checked(&mut deps, mock_env(), mock_info("moderator", &[]), flag_token("Hero.3", true, "disputed")).unwrap();
+
+        // the frozen listing cannot be bought but the seller can take it back
+        let funds = [Coin::new(10, "ujuno")];
+        let buy = |offering_id: &str| ExecuteMsg::BuyNft { offering_id: offering_id.to_string(), recipient: None, msg: None, referrer: None };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("buyer", &funds), buy("1")).unwrap_err();
+        assert!(matches!(err, ContractError::TokenFlagged { .. }));
+        let err = execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), sell("Hero.3")).unwrap_err();
+        assert!(matches!(err, ContractError::TokenFlagged { .. }));
+        let res = execute_checked(&mut deps, mock_env(), mock_info("seller", &[]), ExecuteMsg::WithdrawNft { offering_id: "1".to_string() }).unwrap();
+        assert_eq!(res.messages.len(), 1);
+
+        let msg = ExecuteMsg::FlagAddress { address: "thief".to_string(), flagged: true, reason: "fraud".to_string() };
+        execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+        let err = execute_checked(&mut deps, mock_env(), mock_info("thief", &funds), buy("2")).unwrap_err();
+        assert!(matches!(err, ContractError::AddressBlocked { .. }));
+        let msg = ExecuteMsg::BuyNft { offering_id: "2".to_string(), recipient: Some("thief".to_string()), msg: None, referrer: None };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("buyer", &funds), msg).unwrap_err();
+        assert!(matches!(err, ContractError::AddressBlocked { .. }));
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg{
+            sender:"thief".to_string(),
+            amount:Uint128::new(10),
+            msg:to_binary(&BuyNft{ offering_id:"2".to_string(), recipient:None, msg:None, referrer:None }).unwrap()
+        });
+        let err = execute_checked(&mut deps, mock_env(), mock_info("token_address", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::AddressBlocked { .. }));
+
+        execute_checked(&mut deps, mock_env(), mock_info("moderator", &[]), flag_token("Hero.3", false, "returned to owner")).unwrap();
+        execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), sell("Hero.3")).unwrap();
+
+        let log = query_flag_log(deps.as_ref(), Some(1), None).unwrap().flags;
+        let reasons: Vec<(u64, bool, &str)> = log.iter().map(|flag| (flag.id, flag.record.flagged, flag.record.reason.as_str())).collect();
+        assert_eq!(reasons, vec![(2, true, "disputed"), (3, true, "fraud"), (4, false, "returned to owner")]);
+    }
+
+    #[test]
+    fn referral_fee_share() {
+        let mut deps = mock_dependencies_with_nfts(&[]);
+        deps.querier.set_default_nft_owner(MOCK_CONTRACT_ADDR);
+        let instantiate_msg = InstantiateMsg {
+            royalty_portion:Decimal::percent(10),
+            remainder_address:None,
+            max_royalty_portion:None,
+            treasury:Some("treasury".to_string()),
+            timelock_delay:None
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg).unwrap();
+        accept_denom(deps.as_mut(), "ujuno");
+        for (token_id, denom) in vec![("Hero.1", "ujuno"), ("Hero.2", "ujuno"), ("Hero.3", HOPE_DENOM)] {
+            let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg{
+                sender:"seller".to_string(),
+                token_id:token_id.to_string(),
+                msg:to_binary(&SellNft{
+                    list_price:Asset{ denom:denom.to_string(), amount:Uint128::new(1000) },
+                    reserved_for:None,
+                    alternative_prices:None,
+                    storefront:None,
+                    pull_proceeds:None
+                }).unwrap()
+            });
+            execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), msg).unwrap();
+        }
+
+        let msg = ExecuteMsg::SetReferrer { address: "frontend".to_string(), share: Some(Decimal::percent(101)) };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::WrongPortionError {}));
+        let msg = ExecuteMsg::SetReferrer { address: "frontend".to_string(), share: Some(Decimal::percent(25)) };
+        execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+        let funds = [Coin::new(1000, "ujuno")];
+        let buy = |offering_id: &str, referrer: &str| ExecuteMsg::BuyNft {
+            offering_id: offering_id.to_string(),
+            recipient: None,
+            msg: None,
+            referrer: Some(referrer.to_string()),
+        };
+        let res = execute_checked(&mut deps, mock_env(), mock_info("buyer", &funds), buy("1", "frontend")).unwrap();
+        let payouts: Vec<CosmosMsg> = res.messages.iter().skip(1).map(|sub| sub.msg.clone()).collect();
+        assert_eq!(payouts, vec![
+            CosmosMsg::Bank(BankMsg::Send { to_address: "seller".to_string(), amount: vec![Coin::new(900, "ujuno")] }),
+            CosmosMsg::Bank(BankMsg::Send { to_address: "frontend".to_string(), amount: vec![Coin::new(25, "ujuno")] }),
+            CosmosMsg::Bank(BankMsg::Send { to_address: "treasury".to_string(), amount: vec![Coin::new(75, "ujuno")] }),
+        ]);
+        confirm_submessages(&mut deps, &res);
+
+        // unregistered referrers get nothing
+        let res = execute_checked(&mut deps, mock_env(), mock_info("buyer", &funds), buy("2", "stranger")).unwrap();
+        assert_eq!(res.messages.len(), 3);
+
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg{
+            sender:"buyer".to_string(),
+            amount:Uint128::new(1000),
+            msg:to_binary(&BuyNft{
+                offering_id:"3".to_string(),
+                recipient:None,
+                msg:None,
+                referrer:Some("frontend".to_string())
+            }).unwrap()
+        });
+        let res = execute_checked(&mut deps, mock_env(), mock_info("token_address", &[]), msg).unwrap();
+        confirm_submessages(&mut deps, &res);
+
+        let res = query_referrer(deps.as_ref(), "frontend".to_string()).unwrap();
+        assert_eq!(res, ReferrerResponse {
+            share: Some(Decimal::percent(25)),
+            earned: vec![
+                Asset { denom: HOPE_DENOM.to_string(), amount: Uint128::new(25) },
+                Asset { denom: "ujuno".to_string(), amount: Uint128::new(25) },
+            ],
+        });
+    }
+
+    #[test]
+    fn storefront_listings() {
+        let mut deps = mock_dependencies_with_nfts(&[]);
+        deps.querier.set_default_nft_owner(MOCK_CONTRACT_ADDR);
+        let instantiate_msg = InstantiateMsg {
+            royalty_portion:Decimal::percent(10),
+            remainder_address:None,
+            max_royalty_portion:None,
+            treasury:Some("treasury".to_string()),
+            timelock_delay:None
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg).unwrap();
+        accept_denom(deps.as_mut(), "ujuno");
+        let register = ExecuteMsg::RegisterCollection { address: "items".to_string() };
+        execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), register).unwrap();
+
+        let recipients = vec![UserInfo{
+            address:"partner1".to_string(),
+            portion:Decimal::one()
+        },UserInfo{
+            address:"partner2".to_string(),
+            portion:Decimal::one()
+        }];
+        let msg = ExecuteMsg::CreateStorefront {
+            owner: "community".to_string(),
+            name: "Community market".to_string(),
+            royalty_portion: Decimal::percent(5),
+            recipients: recipients.clone(),
+            allowed_collections: vec!["nft_address".to_string()],
+        };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("community", &[]), msg.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+        let msg = ExecuteMsg::UpdateStorefront {
+            storefront_id: "1".to_string(),
+            royalty_portion: Decimal::percent(50),
+            recipients,
+            allowed_collections: vec![],
+        };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        let sell = |token_id: &str, storefront: Option<&str>| ExecuteMsg::ReceiveNft(Cw721ReceiveMsg{
+            sender:"seller".to_string(),
+            token_id:token_id.to_string(),
+            msg:to_binary(&SellNft{
+                list_price:Asset{ denom:"ujuno".to_string(), amount:Uint128::new(1000) },
+                reserved_for:None,
+                alternative_prices:None,
+                storefront:storefront.map(|id| id.to_string()),
+                pull_proceeds:None
+            }).unwrap()
+        });
+        let err = execute_checked(&mut deps, mock_env(), mock_info("items", &[]), sell("Sword.1", Some("1"))).unwrap_err();
+        assert!(matches!(err, ContractError::CollectionNotInStorefront { .. }));
+        execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), sell("Hero.1", Some("1"))).unwrap();
+        execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), sell("Hero.2", None)).unwrap();
+
+        let ids = |storefront: Option<&str>| -> Vec<String> {
+            query_get_offerings(deps.as_ref(), None, storefront.map(|id| id.to_string())).unwrap().offerings
+                .into_iter().map(|off| off.id).collect()
+        };
+        assert_eq!(ids(Some("1")), vec!["1".to_string()]);
+        assert_eq!(ids(None), vec!["1".to_string(), "2".to_string()]);
+
+        let funds = [Coin::new(1000, "ujuno")];
+        let msg = ExecuteMsg::BuyNft { offering_id: "1".to_string(), recipient: None, msg: None, referrer: None };
+        let res = execute_checked(&mut deps, mock_env(), mock_info("buyer", &funds), msg).unwrap();
+        let payouts: Vec<CosmosMsg> = res.messages.into_iter().skip(1).map(|sub| sub.msg).collect();
+        assert_eq!(payouts, vec![
+            CosmosMsg::Bank(BankMsg::Send { to_address: "seller".to_string(), amount: vec![Coin::new(850, "ujuno")] }),
+            CosmosMsg::Bank(BankMsg::Send { to_address: "treasury".to_string(), amount: vec![Coin::new(100, "ujuno")] }),
+            CosmosMsg::Bank(BankMsg::Send { to_address: "partner1".to_string(), amount: vec![Coin::new(25, "ujuno")] }),
+            CosmosMsg::Bank(BankMsg::Send { to_address: "partner2".to_string(), amount: vec![Coin::new(25, "ujuno")] }),
+        ]);
+    }
 
-        let info = mock_info("owner", &[]);
-        let msg = ExecuteMsg::SetTokenAddress  { address:"token_address1".to_string()};
-        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-        let state = query_state_info(deps.as_ref()).unwrap();
-        assert_eq!(state.token_address,"token_address1".to_string());
+    #[test]
+    fn hook_notifications() {
+        let mut deps = mock_dependencies_with_nfts(&[]);
+        deps.querier.set_default_nft_owner(MOCK_CONTRACT_ADDR);
+        let instantiate_msg = InstantiateMsg {
+            royalty_portion:Decimal::percent(10),
+            remainder_address:None,
+            max_royalty_portion:None,
+            treasury:None,
+            timelock_delay:None
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg).unwrap();
+        accept_denom(deps.as_mut(), "ujuno");
+        for hook in vec!["loyalty", "analytics"] {
+            let msg = ExecuteMsg::AddHook { address: hook.to_string() };
+            execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+        }
+        let err = execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), ExecuteMsg::AddHook { address: "loyalty".to_string() }).unwrap_err();
+        assert!(matches!(err, ContractError::DuplicateHook { .. }));
 
-        //Change NFT contract Address
+        let price = Asset{ denom:"ujuno".to_string(), amount:Uint128::new(100) };
+        let items = |token_id: &str| vec![NftItem { nft_address: "nft_address".to_string(), token_id: token_id.to_string() }];
+        let hook = |contract_addr: &str, event: MarketplaceEvent, gas_limit: u64| SubMsg::reply_on_error(WasmMsg::Execute {
+            contract_addr: contract_addr.to_string(),
+            msg: to_binary(&MarketplaceHookMsg::MarketplaceEvent(event)).unwrap(),
+            funds: vec![],
+        }, HOOK_REPLY_ID).with_gas_limit(gas_limit);
+        // every notification is capped so a runaway hook can't abort the trade
+        let assert_capped = |res: &Response, gas_limit: u64| {
+            let hooks: Vec<&SubMsg> = res.messages.iter().filter(|sub| sub.reply_on == ReplyOn::Error).collect();
+            assert!(!hooks.is_empty());
+            assert!(hooks.iter().all(|sub| sub.id == HOOK_REPLY_ID && sub.gas_limit == Some(gas_limit)));
+        };
+        for token_id in vec!["Hero.1", "Hero.2"] {
+            let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg{
+                sender:"seller".to_string(),
+                token_id:token_id.to_string(),
+                msg:to_binary(&SellNft{
+                    list_price:price.clone(),
+                    reserved_for:None,
+                    alternative_prices:None,
+                    storefront:None,
+                    pull_proceeds:None
+                }).unwrap()
+            });
+            let res = execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), msg).unwrap();
+            assert_eq!(res.messages.len(), 2);
+            assert_capped(&res, DEFAULT_HOOK_GAS_LIMIT);
+        }
 
-        let info = mock_info("owner", &[]);
-        let msg = ExecuteMsg::SetNftAddress  { address:"nft_address1".to_string()};
-        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-        let state = query_state_info(deps.as_ref()).unwrap();
-        assert_eq!(state.nft_address,"nft_address1".to_string());
-        
-        //Send NFT to marketplace contract
+        let msg = ExecuteMsg::BuyNft { offering_id: "1".to_string(), recipient: None, msg: None, referrer: None };
+        let res = execute_checked(&mut deps, mock_env(), mock_info("buyer", &[Coin::new(100, "ujuno")]), msg).unwrap();
+        let sold = MarketplaceEvent::Sold {
+            offering_id: "1".to_string(),
+            seller: "seller".to_string(),
+            buyer: "buyer".to_string(),
+            items: items("Hero.1"),
+            price: price.clone(),
+        };
+        assert_eq!(res.messages[res.messages.len() - 2..], [
+            hook("loyalty", sold.clone(), DEFAULT_HOOK_GAS_LIMIT),
+            hook("analytics", sold, DEFAULT_HOOK_GAS_LIMIT),
+        ]);
+        assert_capped(&res, DEFAULT_HOOK_GAS_LIMIT);
 
-        let cw721_msg = SellNft{
-            list_price:Asset{
-                denom:"ujuno".to_string(),
-                amount:Uint128::new(2)
-            }
+        let msg = ExecuteMsg::SetHookGasLimit { gas_limit: 0 };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::ZeroGasLimit {}));
+        let msg = ExecuteMsg::SetHookGasLimit { gas_limit: 50_000 };
+        execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+        let msg = ExecuteMsg::RemoveHook { address: "analytics".to_string() };
+        execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+        let msg = ExecuteMsg::WithdrawNft { offering_id: "2".to_string() };
+        let res = execute_checked(&mut deps, mock_env(), mock_info("seller", &[]), msg).unwrap();
+        assert_eq!(res.messages[1..], [hook("loyalty", MarketplaceEvent::Cancelled {
+            offering_id: "2".to_string(),
+            seller: "seller".to_string(),
+            items: items("Hero.2"),
+        }, 50_000)]);
+        assert_capped(&res, 50_000);
+
+        // stale approval listings are reported as cancelled when they are dropped
+        let list = |token_id: &str| ExecuteMsg::ListWithApproval {
+            nft_address: None,
+            token_id: token_id.to_string(),
+            list_price: price.clone(),
+            storefront: None,
         };
+        for token_id in vec!["Hero.3", "Hero.4"] {
+            deps.querier.set_nft_owner("nft_address", token_id, "alice", &[MOCK_CONTRACT_ADDR]);
+            execute_checked(&mut deps, mock_env(), mock_info("alice", &[]), list(token_id)).unwrap();
+        }
+        let cancelled = |offering_id: &str, token_id: &str| hook("loyalty", MarketplaceEvent::Cancelled {
+            offering_id: offering_id.to_string(),
+            seller: "alice".to_string(),
+            items: items(token_id),
+        }, 50_000);
+        deps.querier.set_nft_owner("nft_address", "Hero.3", "bob", &[MOCK_CONTRACT_ADDR]);
+        let res = execute_checked(&mut deps, mock_env(), mock_info("bob", &[]), list("Hero.3")).unwrap();
+        assert_eq!(res.messages[0], cancelled("3", "Hero.3"));
+        deps.querier.set_nft_owner("nft_address", "Hero.4", "alice", &[]);
+        let msg = ExecuteMsg::BuyNft { offering_id: "4".to_string(), recipient: None, msg: None, referrer: None };
+        let res = execute_checked(&mut deps, mock_env(), mock_info("buyer", &[Coin::new(100, "ujuno")]), msg).unwrap();
+        assert_eq!(res.messages[1], cancelled("4", "Hero.4"));
 
-        let info = mock_info("nft_address1", &[]);
-        let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg{
-            sender:"owner1".to_string(),
-            token_id:"Hope.1".to_string(),
-            msg:to_binary(&cw721_msg).unwrap()
-        });
-        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
-        assert_eq!(0,res.messages.len());
+        // a failing hook is reported but does not fail the trade
+        let failed = Reply { id: HOOK_REPLY_ID, result: ContractResult::Err("out of points".to_string()) };
+        let res = reply(deps.as_mut(), mock_env(), failed).unwrap();
+        assert_eq!(res.attributes[0].value, "hook_failed".to_string());
+        let unknown = Reply { id: 99, result: ContractResult::Err("boom".to_string()) };
+        reply(deps.as_mut(), mock_env(), unknown).unwrap_err();
+    }
 
+    #[test]
+    fn settlement_replies() {
+        let mut deps = mock_dependencies_with_nfts(&[]);
+        deps.querier.set_default_nft_owner(MOCK_CONTRACT_ADDR);
+        let instantiate_msg = InstantiateMsg {
+            royalty_portion:Decimal::percent(10),
+            remainder_address:None,
+            max_royalty_portion:None,
+            treasury:Some("treasury".to_string()),
+            timelock_delay:None
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg).unwrap();
+        accept_denom(deps.as_mut(), "ujuno");
         let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg{
-            sender:"owner2".to_string(),
-            token_id:"Hope.2".to_string(),
-            msg:to_binary(&cw721_msg).unwrap()
+            sender:"seller".to_string(),
+            token_id:"Hero.1".to_string(),
+            msg:to_binary(&SellNft{
+                list_price:Asset{ denom:"ujuno".to_string(), amount:Uint128::new(1000) },
+                reserved_for:None,
+                alternative_prices:None,
+                storefront:None,
+                pull_proceeds:None
+            }).unwrap()
         });
-        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-        assert_eq!(0,res.messages.len());
+        execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), msg).unwrap();
 
-        let nft_market_datas = query_get_offerings(deps.as_ref()).unwrap();
-        assert_eq!(nft_market_datas.offerings,
-            vec![
-                QueryOfferingsResult{
-                    id :"1".to_string(),
-                    token_id:"Hope.1".to_string(),
-                    seller : "owner1".to_string(),
-                    list_price:Asset { 
-                        denom: "ujuno".to_string(),
-                        amount: Uint128::new(2) 
-                    }
-                },
-                QueryOfferingsResult{
-                    id :"2".to_string(),
-                    token_id:"Hope.2".to_string(),
-                    seller : "owner2".to_string(),
-                    list_price:Asset { 
-                        denom: "ujuno".to_string(),
-                        amount: Uint128::new(2) 
-                    }
-                }
-            ]
-        );
+        let msg = ExecuteMsg::BuyNft { offering_id: "1".to_string(), recipient: None, msg: None, referrer: None };
+        let res = execute_checked(&mut deps, mock_env(), mock_info("buyer", &[Coin::new(1000, "ujuno")]), msg).unwrap();
+        let reply_on: Vec<ReplyOn> = res.messages.iter().map(|sub| sub.reply_on.clone()).collect();
+        assert_eq!(reply_on, vec![ReplyOn::Success, ReplyOn::Always, ReplyOn::Always]);
 
-        //Withdraw nft from market place
+        // nothing is recorded until the token is delivered
+        assert!(SETTLEMENTS.has(deps.as_ref().storage, "1"));
+        let fee = query_fee_for(deps.as_ref(), mock_env(), "buyer".to_string(), "ujuno".to_string()).unwrap();
+        assert_eq!(fee.volume, Uint128::zero());
+        let delivered = Reply {
+            id: res.messages[0].id,
+            result: ContractResult::Ok(SubMsgExecutionResponse { events: vec![], data: None }),
+        };
+        let recorded = reply(deps.as_mut(), mock_env(), delivered.clone()).unwrap();
+        assert_eq!(recorded.attributes[0].value, "sale_recorded".to_string());
+        assert!(!SETTLEMENTS.has(deps.as_ref().storage, "1"));
+        let fee = query_fee_for(deps.as_ref(), mock_env(), "seller".to_string(), "ujuno".to_string()).unwrap();
+        assert_eq!(fee.volume, Uint128::new(1000));
+        reply(deps.as_mut(), mock_env(), delivered).unwrap_err();
 
-        let info = mock_info("owner1", &[]);
-        let msg = ExecuteMsg::WithdrawNft { offering_id: "1".to_string() };
-        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
-        assert_eq!(1,res.messages.len());
-        assert_eq!(res.messages[0].msg, CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: "nft_address1".to_string(),
-            funds: vec![],
-            msg: to_binary(&Cw721ExecuteMsg::TransferNft {
-                    recipient: "owner1".to_string(),
-                    token_id: "Hope.1".to_string(),
-            }).unwrap(),
-        }));      
-        
-        let nft_market_datas = query_get_offerings(deps.as_ref()).unwrap();
-        assert_eq!(nft_market_datas.offerings,
-            vec![
-                QueryOfferingsResult{
-                    id :"2".to_string(),
-                    token_id:"Hope.2".to_string(),
-                    seller : "owner2".to_string(),
-                    list_price:Asset { 
-                        denom: "ujuno".to_string(),
-                        amount: Uint128::new(2) 
-                    }
-                }
-            ]
-        );
+        // a rejected payout becomes claimable instead of reverting the sale
+        let rejected = Reply { id: res.messages[1].id, result: ContractResult::Err("rejected".to_string()) };
+        let failed = reply(deps.as_mut(), mock_env(), rejected).unwrap();
+        assert_eq!(failed.attributes[0].value, "payout_failed".to_string());
+        deps.querier.receive_coins(&[Coin::new(900, "ujuno")]);
+        let paid = Reply {
+            id: res.messages[2].id,
+            result: ContractResult::Ok(SubMsgExecutionResponse { events: vec![], data: None }),
+        };
+        reply(deps.as_mut(), mock_env(), paid).unwrap();
 
-        //Send NFT to marketplace contract
+        let claimable = query_claimable(deps.as_ref(), "seller".to_string()).unwrap();
+        assert_eq!(claimable.balances, vec![Asset { denom: "ujuno".to_string(), amount: Uint128::new(900) }]);
+        let claimable = query_claimable(deps.as_ref(), "treasury".to_string()).unwrap();
+        assert_eq!(claimable.balances, vec![]);
 
-        let cw721_msg = SellNft{
-            list_price:Asset{
-                denom:"hope".to_string(),
-                amount:Uint128::new(10)
-            }
+        let msg = ExecuteMsg::ClaimBalance { denom: "ujuno".to_string() };
+        let res = execute_checked(&mut deps, mock_env(), mock_info("seller", &[]), msg.clone()).unwrap();
+        assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+            to_address: "seller".to_string(),
+            amount: vec![Coin::new(900, "ujuno")],
+        }));
+        let err = execute_checked(&mut deps, mock_env(), mock_info("seller", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::NoData {}));
+    }
+
+    #[test]
+    fn receive_custody_checks() {
+        let mut deps = mock_dependencies_with_nfts(&[]);
+        let instantiate_msg = InstantiateMsg {
+            royalty_portion:Decimal::percent(2),
+            remainder_address:None,
+            max_royalty_portion:None,
+            treasury:None,
+            timelock_delay:None
         };
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg).unwrap();
+        accept_denom(deps.as_mut(), "ujuno");
+        let sell = |token_id: &str, denom: &str, amount: u128| ExecuteMsg::ReceiveNft(Cw721ReceiveMsg{
+            sender:"seller".to_string(),
+            token_id:token_id.to_string(),
+            msg:to_binary(&SellNft{
+                list_price:Asset{ denom:denom.to_string(), amount:Uint128::new(amount) },
+                reserved_for:None,
+                alternative_prices:None,
+                storefront:None,
+                pull_proceeds:None
+            }).unwrap()
+        });
 
-        let info = mock_info("nft_address1", &[]);
-        let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg{
-            sender:"owner3".to_string(),
-            token_id:"Hope.3".to_string(),
-            msg:to_binary(&cw721_msg).unwrap()
+        // a hook for a token the marketplace never received is rejected
+        deps.querier.set_nft_owner("nft_address", "Hero.1", "seller", &[MOCK_CONTRACT_ADDR]);
+        let err = execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), sell("Hero.1", "ujuno", 100)).unwrap_err();
+        assert!(matches!(err, ContractError::NotInCustody { .. }));
+        let err = execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), sell("Hero.9", "ujuno", 100)).unwrap_err();
+        assert!(matches!(err, ContractError::NotInCustody { .. }));
+
+        deps.querier.set_nft_owner("nft_address", "Hero.1", MOCK_CONTRACT_ADDR, &[]);
+        let err = execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), sell("Hero.1", "ujuno", 0)).unwrap_err();
+        assert!(matches!(err, ContractError::PriceTooLow { .. }));
+        let err = execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), sell("Hero.1", "", 100)).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidDenom { .. }));
+        let malformed = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg{
+            sender:"seller".to_string(),
+            token_id:"Hero.1".to_string(),
+            msg:Binary::from(br#"{"list_price":{"denom":"ujuno","amount":"-5"},"reserved_for":null}"#.to_vec())
         });
-        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), malformed).unwrap_err();
+        assert_eq!(CONFIG.load(deps.as_ref().storage).unwrap().offering_id, 0);
 
-        let nft_market_datas = query_get_offerings(deps.as_ref()).unwrap();
-        assert_eq!(nft_market_datas.offerings,
-            vec![            
-                QueryOfferingsResult{
-                    id :"2".to_string(),
-                    token_id:"Hope.2".to_string(),
-                    seller : "owner2".to_string(),
-                    list_price:Asset { 
-                        denom: "ujuno".to_string(),
-                        amount: Uint128::new(2) 
-                    }
-                }, QueryOfferingsResult{
-                    id :"3".to_string(),
-                    token_id:"Hope.3".to_string(),
-                    seller : "owner3".to_string(),
-                    list_price:Asset { 
-                        denom: "hope".to_string(),
-                        amount: Uint128::new(10) 
-                    }
-                }
-            ]
-        );
+        // the same token can't be listed twice, by escrow or by approval
+        execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), sell("Hero.1", "ujuno", 100)).unwrap();
+        let err = execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), sell("Hero.1", "ujuno", 50)).unwrap_err();
+        assert!(matches!(err, ContractError::AlreadyListed { offering_id, .. } if offering_id == "1"));
+        let msg = ExecuteMsg::ListWithApproval {
+            nft_address: None,
+            token_id: "Hero.1".to_string(),
+            list_price: Asset{ denom:"ujuno".to_string(), amount:Uint128::new(50) },
+            storefront: None,
+        };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("seller", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::AlreadyListed { .. }));
 
-        //Buy nft using token
+        // withdrawing frees the token for a new listing
+        let msg = ExecuteMsg::WithdrawNft { offering_id: "1".to_string() };
+        execute_checked(&mut deps, mock_env(), mock_info("seller", &[]), msg).unwrap();
+        deps.querier.set_nft_owner("nft_address", "Hero.1", MOCK_CONTRACT_ADDR, &[]);
+        execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), sell("Hero.1", "ujuno", 50)).unwrap();
+        // a stale approval listing gives way to the token's new owner
+        let list = |token_id: &str| ExecuteMsg::ListWithApproval {
+            nft_address: None,
+            token_id: token_id.to_string(),
+            list_price: Asset{ denom:"ujuno".to_string(), amount:Uint128::new(50) },
+            storefront: None,
+        };
+        for token_id in vec!["Hero.5", "Hero.6"] {
+            deps.querier.set_nft_owner("nft_address", token_id, "alice", &[MOCK_CONTRACT_ADDR]);
+            execute_checked(&mut deps, mock_env(), mock_info("alice", &[]), list(token_id)).unwrap();
+        }
+        deps.querier.set_nft_owner("nft_address", "Hero.5", "bob", &[MOCK_CONTRACT_ADDR]);
+        let err = execute_checked(&mut deps, mock_env(), mock_info("bob", &[]), list("Hero.6")).unwrap_err();
+        assert!(matches!(err, ContractError::AlreadyListed { .. }));
+        execute_checked(&mut deps, mock_env(), mock_info("bob", &[]), list("Hero.5")).unwrap();
+        deps.querier.set_nft_owner("nft_address", "Hero.6", MOCK_CONTRACT_ADDR, &[]);
+        let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg{
+            sender:"bob".to_string(),
+            token_id:"Hero.6".to_string(),
+            msg:to_binary(&SellNft{
+                list_price:Asset{ denom:"ujuno".to_string(), amount:Uint128::new(50) },
+                reserved_for:None,
+                alternative_prices:None,
+                storefront:None,
+                pull_proceeds:None
+            }).unwrap()
+        });
+        execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), msg).unwrap();
+        let sellers: Vec<(String, String)> = query_get_offerings(deps.as_ref(), None, None).unwrap().offerings
+            .into_iter().map(|o| (o.token_id, o.seller)).collect();
+        assert_eq!(sellers, vec![
+            ("Hero.1".to_string(), "seller".to_string()),
+            ("Hero.5".to_string(), "bob".to_string()),
+            ("Hero.6".to_string(), "bob".to_string()),
+        ]);
+    }
 
-        let cw20_msg = BuyNft{
-             offering_id:"3".to_string()
+    #[test]
+    fn rescue_stray_assets() {
+        let mut deps = mock_dependencies_with_nfts(&[Coin::new(700, "ujuno")]);
+        let instantiate_msg = InstantiateMsg {
+            royalty_portion:Decimal::percent(2),
+            remainder_address:None,
+            max_royalty_portion:None,
+            treasury:None,
+            timelock_delay:None
         };
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg).unwrap();
+        accept_denom(deps.as_mut(), "ujuno");
+        for token_id in vec!["Stray.1", "Hero.1", "Hero.3"] {
+            deps.querier.set_nft_owner("nft_address", token_id, MOCK_CONTRACT_ADDR, &[]);
+        }
+        deps.querier.set_nft_owner("nft_address", "Hero.2", "seller", &[]);
 
-        let info = mock_info("token_address1", &[]);
-        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg{
-            sender:"buyer".to_string(),
-            amount:Uint128::new(1000),
-            msg:to_binary(&cw20_msg).unwrap()
+        // Hero.1 is listed and Hero.3 escrowed for a swap with a 300ujuno sweetener
+        let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg{
+            sender:"seller".to_string(),
+            token_id:"Hero.1".to_string(),
+            msg:to_binary(&SellNft{
+                list_price:Asset{ denom:"ujuno".to_string(), amount:Uint128::new(100) },
+                reserved_for:None,
+                alternative_prices:None,
+                storefront:None,
+                pull_proceeds:None
+            }).unwrap()
         });
-        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
-        assert_eq!(4,res.messages.len());
+        execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), msg).unwrap();
+        let item = |token_id: &str| NftItem { nft_address: "nft_address".to_string(), token_id: token_id.to_string() };
+        let proposal = ProposeSwap { offered: vec![item("Hero.3")], wanted: item("Hero.4"), end_height: None, end_time: None };
+        execute_checked(&mut deps, mock_env(), mock_info("alice", &[Coin::new(300, "ujuno")]), ExecuteMsg::ProposeSwap(proposal)).unwrap();
+        let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg{
+            sender:"alice".to_string(),
+            token_id:"Hero.3".to_string(),
+            msg:to_binary(&Cw721HookMsg::DepositSwap { swap_id: "1".to_string() }).unwrap()
+        });
+        execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), msg).unwrap();
+
+        let rescue_nft = |token_id: &str| ExecuteMsg::RescueNft {
+            nft_address: "nft_address".to_string(),
+            token_id: token_id.to_string(),
+            recipient: "sender".to_string(),
+        };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("stranger", &[]), rescue_nft("Stray.1")).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        let err = execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), rescue_nft("Hero.2")).unwrap_err();
+        assert!(matches!(err, ContractError::NotInCustody { .. }));
+        for token_id in vec!["Hero.1", "Hero.3"] {
+            let err = execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), rescue_nft(token_id)).unwrap_err();
+            assert!(matches!(err, ContractError::AssetInUse { .. }));
+        }
+        let res = execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), rescue_nft("Stray.1")).unwrap();
         assert_eq!(res.messages[0].msg, CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: "nft_address1".to_string(),
-            funds: vec![],
-            msg: to_binary(&Cw721ExecuteMsg::TransferNft {
-                    recipient: "buyer".to_string(),
-                    token_id: "Hope.3".to_string(),
-            }).unwrap(),
-        }));
-        assert_eq!(res.messages[1].msg, CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: "token_address1".to_string(),
+            contract_addr: "nft_address".to_string(),
             funds: vec![],
-            msg: to_binary(&Cw20ExecuteMsg::Transfer {
-                    recipient: "owner3".to_string(),
-                    amount:Uint128::new(970)
-            }).unwrap(),
+            msg: to_binary(&Cw721ExecuteMsg::TransferNft { recipient: "sender".to_string(), token_id: "Stray.1".to_string() }).unwrap(),
         }));
 
-        assert_eq!(res.messages[2].msg.clone(), CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: "token_address1".to_string(),
-            funds: vec![],
-            msg: to_binary(&Cw20ExecuteMsg::Transfer {
-                    recipient: "admin1".to_string(),
-                    amount:Uint128::new(9)
-            }).unwrap(),
+        // the sweetener is owed to the swap, only the rest can be rescued
+        let rescue_coins = |amount: u128| ExecuteMsg::RescueCoins {
+            denom: "ujuno".to_string(),
+            amount: Uint128::new(amount),
+            recipient: "sender".to_string(),
+        };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), rescue_coins(701)).unwrap_err();
+        assert!(matches!(err, ContractError::RescueExceedsFree { available, .. } if available == Uint128::new(700)));
+        let res = execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), rescue_coins(700)).unwrap();
+        assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+            to_address: "sender".to_string(),
+            amount: vec![Coin::new(700, "ujuno")],
         }));
 
-
-        assert_eq!(res.messages[3].msg, CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: "token_address1".to_string(),
+        // a HOPE collection fee under review is owed as well
+        let fee = Asset { denom: HOPE_DENOM.to_string(), amount: Uint128::new(200) };
+        let msg = ExecuteMsg::SetCollectionFee { fee: Some(fee) };
+        execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), msg).unwrap();
+        deps.querier.set_minter("items", "creator");
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg{
+            sender:"creator".to_string(),
+            amount:Uint128::new(200),
+            msg:to_binary(&Cw20HookMsg::SubmitCollection(SubmitCollection {
+                address: "items".to_string(),
+                name: "Items".to_string(),
+                description: "A collection".to_string(),
+            })).unwrap()
+        });
+        execute_checked(&mut deps, mock_env(), mock_info("token_address", &[]), msg).unwrap();
+        deps.querier.set_token_balance("token_address", MOCK_CONTRACT_ADDR, 500);
+        deps.querier.set_token_balance("other_token", MOCK_CONTRACT_ADDR, 50);
+        let rescue_cw20 = |token_address: &str, amount: u128| ExecuteMsg::RescueCw20 {
+            token_address: token_address.to_string(),
+            amount: Uint128::new(amount),
+            recipient: "sender".to_string(),
+        };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), rescue_cw20("t", 1)).unwrap_err();
+        assert!(matches!(err, ContractError::Std(StdError::GenericErr { .. })));
+        let err = execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), rescue_cw20("token_address", 400)).unwrap_err();
+        assert!(matches!(err, ContractError::RescueExceedsFree { available, .. } if available == Uint128::new(300)));
+        execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), rescue_cw20("token_address", 300)).unwrap();
+        let res = execute_checked(&mut deps, mock_env(), mock_info("owner", &[]), rescue_cw20("other_token", 50)).unwrap();
+        assert_eq!(res.messages[0].msg, CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "other_token".to_string(),
             funds: vec![],
-            msg: to_binary(&Cw20ExecuteMsg::Transfer {
-                    recipient: "admin2".to_string(),
-                    amount:Uint128::new(21)
-            }).unwrap(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer { recipient: "sender".to_string(), amount: Uint128::new(50) }).unwrap(),
         }));
+    }
 
-        let nft_market_datas = query_get_offerings(deps.as_ref()).unwrap();
-        assert_eq!(nft_market_datas.offerings,
-            vec![            
-                QueryOfferingsResult{
-                    id :"2".to_string(),
-                    token_id:"Hope.2".to_string(),
-                    seller : "owner2".to_string(),
-                    list_price:Asset { 
-                        denom: "ujuno".to_string(),
-                        amount: Uint128::new(2) 
-                    }
-                }
-            ]
-        );
+    #[test]
+    fn solvency_report() {
+        let mut deps = mock_dependencies_with_nfts(&[]);
+        deps.querier.set_default_nft_owner(MOCK_CONTRACT_ADDR);
+        let instantiate_msg = InstantiateMsg {
+            royalty_portion:Decimal::percent(2),
+            remainder_address:None,
+            max_royalty_portion:None,
+            treasury:None,
+            timelock_delay:None
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg).unwrap();
+        accept_denom(deps.as_mut(), "ujuno");
+        let res = query_solvency(deps.as_ref(), mock_env()).unwrap();
+        assert_eq!(res, SolvencyResponse { solvent: true, funds: vec![], missing_nfts: vec![] });
 
-        //Buy nft using stable coin 
+        let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg{
+            sender:"seller".to_string(),
+            token_id:"Hero.1".to_string(),
+            msg:to_binary(&SellNft{
+                list_price:Asset{ denom:"ujuno".to_string(), amount:Uint128::new(100) },
+                reserved_for:None,
+                alternative_prices:None,
+                storefront:None,
+                pull_proceeds:None
+            }).unwrap()
+        });
+        execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), msg).unwrap();
+        let item = |token_id: &str| NftItem { nft_address: "nft_address".to_string(), token_id: token_id.to_string() };
+        let proposal = ProposeSwap { offered: vec![item("Hero.2")], wanted: item("Hero.3"), end_height: None, end_time: None };
+        execute_checked(&mut deps, mock_env(), mock_info("alice", &[Coin::new(300, "ujuno")]), ExecuteMsg::ProposeSwap(proposal)).unwrap();
 
-        let info = mock_info("buyer2", &[Coin{
-            denom:"ujuno".to_string(),
-            amount:Uint128::new(1000)
-        }]);
-        let msg = ExecuteMsg::BuyNft { offering_id: "2".to_string() };
-        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
-        assert_eq!(res.messages.len(),4);
-        assert_eq!(res.messages[0].msg,CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: state.nft_address.to_string(),
-                funds: vec![],
-                msg: to_binary(&Cw721ExecuteMsg::TransferNft {
-                    recipient: "buyer2".to_string(),
-                    token_id: "Hope.2".to_string(),
+        // losing an escrowed token or owed coins shows up as a shortfall
+        deps.querier.set_nft_owner("nft_address", "Hero.1", "thief", &[]);
+        deps.querier.apply_messages(&[SubMsg::new(BankMsg::Send {
+            to_address: "thief".to_string(),
+            amount: vec![Coin::new(120, "ujuno")],
+        })]);
+        let res = query_solvency(deps.as_ref(), mock_env()).unwrap();
+        assert_eq!(res, SolvencyResponse {
+            solvent: false,
+            funds: vec![FundsSolvency {
+                denom: "ujuno".to_string(),
+                owed: Uint128::new(300),
+                held: Uint128::new(180),
+                shortfall: Uint128::new(120),
+            }],
+            missing_nfts: vec![item("Hero.1")],
+        });
+    }
+
+    #[test]
+    fn pull_proceeds() {
+        let mut deps = mock_dependencies_with_nfts(&[]);
+        deps.querier.set_default_nft_owner(MOCK_CONTRACT_ADDR);
+        let instantiate_msg = InstantiateMsg {
+            royalty_portion:Decimal::percent(10),
+            remainder_address:None,
+            max_royalty_portion:None,
+            treasury:Some("treasury".to_string()),
+            timelock_delay:None
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), instantiate_msg).unwrap();
+        accept_denom(deps.as_mut(), "ujuno");
+        let sell = |seller: &str, token_id: &str, pull_proceeds: Option<bool>| ExecuteMsg::ReceiveNft(Cw721ReceiveMsg{
+            sender:seller.to_string(),
+            token_id:token_id.to_string(),
+            msg:to_binary(&SellNft{
+                list_price:Asset{ denom:"ujuno".to_string(), amount:Uint128::new(1000) },
+                reserved_for:None,
+                alternative_prices:None,
+                storefront:None,
+                pull_proceeds
             }).unwrap()
-        }));
-        assert_eq!(res.messages[1].msg,CosmosMsg::Bank(BankMsg::Send {
-                to_address: "owner2".to_string(),
-                amount:vec![Coin{
-                    denom:"ujuno".to_string(),
-                    amount:Uint128::new(970)
-                }]
-        }));
-        assert_eq!(res.messages[2].msg,CosmosMsg::Bank(BankMsg::Send {
-                to_address: "admin1".to_string(),
-                amount:vec![Coin{
-                    denom:"ujuno".to_string(),
-                    amount:Uint128::new(9)
-                }]
-        }));
-        assert_eq!(res.messages[3].msg,CosmosMsg::Bank(BankMsg::Send {
-                to_address: "admin2".to_string(),
-                amount:vec![Coin{
-                    denom:"ujuno".to_string(),
-                    amount:Uint128::new(21)
-                }]
-        }));
+        });
+        let buy = |offering_id: &str| ExecuteMsg::BuyNft { offering_id: offering_id.to_string(), recipient: None, msg: None, referrer: None };
+        let treasury_fee = CosmosMsg::Bank(BankMsg::Send { to_address: "treasury".to_string(), amount: vec![Coin::new(100, "ujuno")] });
 
-        let nft_market_datas = query_get_offerings(deps.as_ref()).unwrap();
-        assert_eq!(nft_market_datas.offerings,
-            vec![]
-        );
+        // per listing
+        execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), sell("seller", "Hero.1", Some(true))).unwrap();
+        let res = execute_checked(&mut deps, mock_env(), mock_info("buyer", &[Coin::new(1000, "ujuno")]), buy("1")).unwrap();
+        let payouts: Vec<CosmosMsg> = res.messages.iter().skip(1).map(|sub| sub.msg.clone()).collect();
+        assert_eq!(payouts, vec![treasury_fee.clone()]);
+
+        // per account, for every listing of the seller
+        let msg = ExecuteMsg::SetPullProceeds { pull: true };
+        execute_checked(&mut deps, mock_env(), mock_info("vault", &[]), msg).unwrap();
+        execute_checked(&mut deps, mock_env(), mock_info("nft_address", &[]), sell("vault", "Hero.2", None)).unwrap();
+        let res = execute_checked(&mut deps, mock_env(), mock_info("buyer", &[Coin::new(1000, "ujuno")]), buy("2")).unwrap();
+        let payouts: Vec<CosmosMsg> = res.messages.iter().skip(1).map(|sub| sub.msg.clone()).collect();
+        assert_eq!(payouts, vec![treasury_fee]);
+
+        let proceeds = query_proceeds(deps.as_ref(), "vault".to_string()).unwrap();
+        assert_eq!(proceeds, ProceedsResponse {
+            pull: true,
+            balances: vec![Asset { denom: "ujuno".to_string(), amount: Uint128::new(900) }],
+        });
+        let withdraw = |amount: u128| ExecuteMsg::WithdrawProceeds {
+            asset: Asset { denom: "ujuno".to_string(), amount: Uint128::new(amount) },
+        };
+        let err = execute_checked(&mut deps, mock_env(), mock_info("vault", &[]), withdraw(901)).unwrap_err();
+        assert!(matches!(err, ContractError::NotEnoughFunds {}));
+        let res = execute_checked(&mut deps, mock_env(), mock_info("vault", &[]), withdraw(600)).unwrap();
+        assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+            to_address: "vault".to_string(),
+            amount: vec![Coin::new(600, "ujuno")],
+        }));
+        let proceeds = query_proceeds(deps.as_ref(), "vault".to_string()).unwrap();
+        assert_eq!(proceeds.balances, vec![Asset { denom: "ujuno".to_string(), amount: Uint128::new(300) }]);
+        let proceeds = query_proceeds(deps.as_ref(), "seller".to_string()).unwrap();
+        assert_eq!(proceeds, ProceedsResponse {
+            pull: false,
+            balances: vec![Asset { denom: "ujuno".to_string(), amount: Uint128::new(900) }],
+        });
     }
 }
-    
-  
\ No newline at end of file
@@ -1,7 +1,9 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{Decimal, StdError, Uint128};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
+// `WrongNFTContractError` and `WrongTokenContractError` are part of the public API
+#[allow(clippy::enum_variant_names)]
 pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
@@ -15,6 +17,114 @@ pub enum ContractError {
     #[error("Sum of portion is not 1 ")]
     PortionError{},
 
+    #[error("Royalty portion {royalty_portion} exceeds the maximum of {max_royalty_portion}")]
+    RoyaltyTooHigh {
+        royalty_portion: Decimal,
+        max_royalty_portion: Decimal,
+    },
+
+    #[error("Member {address} already exists")]
+    DuplicateMember { address: String },
+
+    #[error("Member {address} not found")]
+    MemberNotFound { address: String },
+
+    #[error("Portion must not be zero")]
+    ZeroPortion {},
+
+    #[error("Config change can not be applied before {executable_at}")]
+    ChangeNotReady { executable_at: u64 },
+
+    #[error("Timelock delay {delay} exceeds the maximum of {max_delay} seconds")]
+    TimelockTooLong { delay: u64, max_delay: u64 },
+
+    #[error("Fee tiers must have distinct minimum volumes")]
+    DuplicateFeeTier {},
+
+    #[error("Seller does not own the token or the marketplace is not approved")]
+    ApprovalMissing {},
+
+    #[error("Token {token_id} appears more than once")]
+    DuplicateToken { token_id: String },
+
+    #[error("No price given for token {token_id}")]
+    MissingPrice { token_id: String },
+
+    #[error("Offering {offering_id} is not available")]
+    OfferingUnavailable { offering_id: String },
+
+    #[error("Total {total} exceeds the maximum of {max_total}")]
+    MaxTotalExceeded { total: Uint128, max_total: Uint128 },
+
+    #[error("Send funds in exactly one denom")]
+    InvalidFunds {},
+
+    #[error("Offering is reserved for other buyers")]
+    ReservedListing {},
+
+    #[error("Token {token_id} is not an outstanding piece of the bundle")]
+    NotInBundle { token_id: String },
+
+    #[error("Not all offered tokens are escrowed yet")]
+    SwapNotFunded {},
+
+    #[error("More than one price in {denom}")]
+    DuplicatePrice { denom: String },
+
+    #[error("Listings in {denom} are not accepted")]
+    UnsupportedDenom { denom: String },
+
+    #[error("Price in {denom} is below the minimum of {min_price}")]
+    PriceTooLow { denom: String, min_price: Uint128 },
+
+    #[error("Collection {address} is already registered or under review")]
+    CollectionExists { address: String },
+
+    #[error("Collection {address} is not awaiting review")]
+    NotPending { address: String },
+
+    #[error("Address {address} is blocked: {reason}")]
+    AddressBlocked { address: String, reason: String },
+
+    #[error("Token {token_id} is flagged: {reason}")]
+    TokenFlagged { token_id: String, reason: String },
+
+    #[error("Storefront {storefront_id} does not list this collection")]
+    CollectionNotInStorefront { storefront_id: String },
+
+    #[error("Hook {address} is already registered")]
+    DuplicateHook { address: String },
+
+    #[error("Hook {address} is not registered")]
+    HookNotFound { address: String },
+
+    #[error("Marketplace does not hold token {token_id}")]
+    NotInCustody { token_id: String },
+
+    #[error("Token {token_id} is already listed in offering {offering_id}")]
+    AlreadyListed { token_id: String, offering_id: String },
+
+    #[error("Malformed denom {denom:?}")]
+    InvalidDenom { denom: String },
+
+    #[error("Token {token_id} backs an open offering, bundle or swap")]
+    AssetInUse { token_id: String },
+
+    #[error("Only {available} {denom} is not owed to users")]
+    RescueExceedsFree { denom: String, available: Uint128 },
+
+    #[error("Member portions add up to more than can be split")]
+    WeightOverflow {},
+
+    #[error("HOPE is only accepted through its token contract")]
+    NativeHope {},
+
+    #[error("Hook gas limit must not be zero")]
+    ZeroGasLimit {},
+
+    #[error("Only the minter of {address} can submit it")]
+    NotCollectionMinter { address: String },
+
     #[error("NFT contract Error")]
     WrongNFTContractError{},
     
@@ -32,7 +142,4 @@ pub enum ContractError {
         end_height: Option<u64>,
         end_time: Option<u64>,
     },
-
-    #[error("Escrow not expired")]
-    NotExpired {},
 }
@@ -1,6 +1,12 @@
+// The package name `Hope_MarketPlace` also names the wasm artifact.
+#![allow(non_snake_case)]
+
 pub mod contract;
 mod error;
 pub mod msg;
 pub mod state;
 pub mod asset;
 pub mod package;
+
+#[cfg(test)]
+mod mock_querier;
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+
+use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::{
+    from_binary, from_slice, to_binary, BankMsg, Coin, ContractResult, CosmosMsg, Empty, OwnedDeps,
+    Querier, QuerierResult, QueryRequest, SubMsg, SystemError, SystemResult, Uint128, WasmMsg,
+    WasmQuery,
+};
+use cw20::{BalanceResponse, Cw20ExecuteMsg, Cw20QueryMsg};
+use cw721::{Approval, Cw721ExecuteMsg, Cw721QueryMsg, Expiration, OwnerOfResponse};
+
+use crate::msg::{CollectionQueryMsg, MinterResponse};
+
+/// Mock dependencies whose querier also answers cw721 and cw20 queries.
+pub fn mock_dependencies_with_nfts(
+    contract_balance: &[Coin],
+) -> OwnedDeps<MockStorage, MockApi, WasmMockQuerier> {
+    OwnedDeps {
+        storage: MockStorage::default(),
+        api: MockApi::default(),
+        querier: WasmMockQuerier::new(MockQuerier::new(&[(MOCK_CONTRACT_ADDR, contract_balance)])),
+    }
+}
+
+pub struct WasmMockQuerier {
+    base: MockQuerier<Empty>,
+    /// (collection, token_id) -> owner response
+    nft_owners: HashMap<(String, String), OwnerOfResponse>,
+    /// owner of every token without an entry in `nft_owners`
+    default_nft_owner: Option<String>,
+    /// (token, holder) -> cw20 balance
+    token_balances: HashMap<(String, String), Uint128>,
+    /// collection -> minter
+    minters: HashMap<String, String>,
+}
+
+impl Querier for WasmMockQuerier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        let request: QueryRequest<Empty> = match from_slice(bin_request) {
+            Ok(v) => v,
+            Err(e) => {
+                return SystemResult::Err(SystemError::InvalidRequest {
+                    error: format!("Parsing query request: {}", e),
+                    request: bin_request.into(),
+                })
+            }
+        };
+        self.handle_query(&request)
+    }
+}
+
+impl WasmMockQuerier {
+    pub fn new(base: MockQuerier<Empty>) -> Self {
+        WasmMockQuerier {
+            base,
+            nft_owners: HashMap::new(),
+            default_nft_owner: None,
+            token_balances: HashMap::new(),
+            minters: HashMap::new(),
+        }
+    }
+
+    /// Reports `owner` as the owner of all tokens not set explicitly.
+    pub fn set_default_nft_owner(&mut self, owner: &str) {
+        self.default_nft_owner = Some(owner.to_string());
+    }
+
+    /// Sets the owner of a token and the spenders approved for it.
+    pub fn set_nft_owner(&mut self, collection: &str, token_id: &str, owner: &str, spenders: &[&str]) {
+        self.nft_owners.insert(
+            (collection.to_string(), token_id.to_string()),
+            OwnerOfResponse {
+                owner: owner.to_string(),
+                approvals: spenders
+                    .iter()
+                    .map(|spender| Approval {
+                        spender: spender.to_string(),
+                        expires: Expiration::Never {},
+                    })
+                    .collect(),
+            },
+        );
+    }
+
+    pub fn set_token_balance(&mut self, token: &str, holder: &str, balance: u128) {
+        self.token_balances
+            .insert((token.to_string(), holder.to_string()), Uint128::new(balance));
+    }
+
+    pub fn set_minter(&mut self, collection: &str, minter: &str) {
+        self.minters.insert(collection.to_string(), minter.to_string());
+    }
+
+    /// Adds coins sent along with an execution to the contract's balance.
+    pub fn receive_coins(&mut self, coins: &[Coin]) {
+        for coin in coins {
+            self.adjust_coin(coin, true);
+        }
+    }
+
+    /// Adds cw20 tokens sent through a `Receive` hook to the contract's balance.
+    pub fn receive_tokens(&mut self, token: &str, amount: Uint128) {
+        let balance = self
+            .token_balances
+            .entry((token.to_string(), MOCK_CONTRACT_ADDR.to_string()))
+            .or_default();
+        *balance += amount;
+    }
+
+    /// Moves the contract's coins, cw20 tokens and NFTs the way executing
+    /// `messages` on chain would.
+    pub fn apply_messages(&mut self, messages: &[SubMsg]) {
+        for sub in messages {
+            match &sub.msg {
+                CosmosMsg::Bank(BankMsg::Send { amount, .. }) => {
+                    for coin in amount {
+                        self.adjust_coin(coin, false);
+                    }
+                }
+                CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, msg, funds }) => {
+                    for coin in funds {
+                        self.adjust_coin(coin, false);
+                    }
+                    match from_binary(msg) {
+                        Ok(Cw20ExecuteMsg::Transfer { amount, .. }) | Ok(Cw20ExecuteMsg::Send { amount, .. }) => {
+                            let balance = self
+                                .token_balances
+                                .entry((contract_addr.clone(), MOCK_CONTRACT_ADDR.to_string()))
+                                .or_default();
+                            *balance = balance.checked_sub(amount).unwrap();
+                        }
+                        _ => match from_binary(msg) {
+                            Ok(Cw721ExecuteMsg::TransferNft { recipient, token_id }) => {
+                                self.set_nft_owner(contract_addr, &token_id, &recipient, &[])
+                            }
+                            Ok(Cw721ExecuteMsg::SendNft { contract, token_id, .. }) => {
+                                self.set_nft_owner(contract_addr, &token_id, &contract, &[])
+                            }
+                            _ => {}
+                        },
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn adjust_coin(&mut self, coin: &Coin, credit: bool) {
+        let mut balance = self.base.update_balance(MOCK_CONTRACT_ADDR, vec![]).unwrap_or_default();
+        match balance.iter_mut().find(|held| held.denom == coin.denom) {
+            Some(held) if credit => held.amount += coin.amount,
+            Some(held) => held.amount = held.amount.checked_sub(coin.amount).unwrap(),
+            None if credit => balance.push(coin.clone()),
+            None => panic!("contract holds no {}", coin.denom),
+        }
+        self.base.update_balance(MOCK_CONTRACT_ADDR, balance);
+    }
+
+    pub fn handle_query(&self, request: &QueryRequest<Empty>) -> QuerierResult {
+        match request {
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg }) => {
+                if let Ok(Cw20QueryMsg::Balance { address }) = from_binary(msg) {
+                    let balance = self
+                        .token_balances
+                        .get(&(contract_addr.clone(), address))
+                        .cloned()
+                        .unwrap_or_default();
+                    return SystemResult::Ok(ContractResult::Ok(to_binary(&BalanceResponse { balance }).unwrap()));
+                }
+                if let Ok(CollectionQueryMsg::Minter {}) = from_binary(msg) {
+                    return match self.minters.get(contract_addr) {
+                        Some(minter) => SystemResult::Ok(ContractResult::Ok(
+                            to_binary(&MinterResponse { minter: minter.clone() }).unwrap(),
+                        )),
+                        None => SystemResult::Err(SystemError::NoSuchContract { addr: contract_addr.clone() }),
+                    };
+                }
+                match from_binary(msg) {
+                    Ok(Cw721QueryMsg::OwnerOf { token_id, .. }) => {
+                        let owner = self.nft_owners.get(&(contract_addr.clone(), token_id)).cloned().or_else(|| {
+                            self.default_nft_owner.as_ref().map(|owner| OwnerOfResponse {
+                                owner: owner.clone(),
+                                approvals: vec![],
+                            })
+                        });
+                        match owner {
+                            Some(owner) => SystemResult::Ok(ContractResult::Ok(to_binary(&owner).unwrap())),
+                            None => SystemResult::Ok(ContractResult::Err("token not found".to_string())),
+                        }
+                    }
+                    _ => SystemResult::Err(SystemError::UnsupportedRequest {
+                        kind: "wasm".to_string(),
+                    }),
+                }
+            }
+            _ => self.base.handle_query(request),
+        }
+    }
+}
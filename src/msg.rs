@@ -2,14 +2,26 @@
 use cw20::Cw20ReceiveMsg;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use crate::state::{Asset,UserInfo};
-use cosmwasm_std::{Decimal, Uint128};
+use crate::state::{Asset,UserInfo,FeeTier,NftItem,CollectionStatus};
+use cosmwasm_std::{Binary, Decimal, Uint128};
 use cw721::Cw721ReceiveMsg;
 
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
-    pub royalty_portion:Decimal
+    pub royalty_portion:Decimal,
+    /// Receives the rounding dust left over when the fee is split between
+    /// members. Defaults to the instantiator.
+    pub remainder_address:Option<String>,
+    /// Upper bound for `royalty_portion`. Defaults to one.
+    pub max_royalty_portion:Option<Decimal>,
+    /// Receives the whole fee while no members are configured. Defaults to
+    /// the instantiator.
+    pub treasury:Option<String>,
+    /// Seconds a sensitive config change waits before it can be applied.
+    /// Changes apply immediately while this is zero, the default. At most
+    /// 30 days.
+    pub timelock_delay:Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -17,13 +29,99 @@ pub struct InstantiateMsg {
 pub enum ExecuteMsg {
  ReceiveNft(Cw721ReceiveMsg),
  Receive(Cw20ReceiveMsg),
+ /// Replaces the fee members. Portions are relative weights.
  SetAdminsList{members:Vec<UserInfo>},
+ AddMember { address:String, portion:Decimal },
+ RemoveMember { address:String },
+ UpdateMemberPortion { address:String, portion:Decimal },
  ChangeRoyaltyPortion{royalty_portion:Decimal},
- BuyNft{offering_id:String},
+ /// Buys an offering for `recipient`, the sender by default. With a `msg`
+ /// the token is delivered with cw721 `SendNft`.
+ BuyNft{offering_id:String, recipient:Option<String>, msg:Option<Binary>, referrer:Option<String>},
+ /// Buys several offerings priced in the one native denom sent, each at its
+ /// list price, and refunds the rest. Unavailable offerings fail the whole
+ /// purchase unless `skip_unavailable` is set.
+ BuyMany { offering_ids:Vec<String>, max_total:Uint128, skip_unavailable:bool },
  WithdrawNft{offering_id:String},
+ /// Lists a token the seller keeps in their wallet. The marketplace has to
+ /// be approved for the token with cw721 `Approve`. The collection defaults
+ /// to `State.nft_address`.
+ ListWithApproval { nft_address:Option<String>, token_id:String, list_price:Asset, storefront:Option<String> },
+ /// Lists many approved tokens at once. Tokens without their own price use
+ /// `list_price`.
+ ListManyWithApproval { nft_address:Option<String>, tokens:Vec<ApprovalListing>, list_price:Option<Asset>, storefront:Option<String> },
+ /// Starts a bundle of tokens sold as one offering. The seller escrows each
+ /// token with a `DepositBundle` hook; the bundle is listed once all arrived.
+ CreateBundle { items:Vec<NftItem>, list_price:Asset, reserved_for:Option<Vec<String>> },
+ /// Drops an unfinished bundle and returns the deposited tokens.
+ CancelBundle { bundle_id:String },
+ /// Proposes to trade escrowed tokens, plus any native funds sent along, for
+ /// another token. The offered tokens are escrowed with `DepositSwap` hooks.
+ ProposeSwap(ProposeSwap),
+ /// Refunds a swap proposal. Open proposals can only be cancelled by the
+ /// proposer, expired ones by anyone.
+ CancelSwap { swap_id:String },
+ /// Accepts another cw721 collection besides `State.nft_address`.
+ RegisterCollection { address:String },
+ DeregisterCollection { address:String },
+ /// Submits a collection for review, paying `State.collection_fee` in native
+ /// funds. HOPE fees are paid through `Cw20HookMsg::SubmitCollection`. Only
+ /// the collection's cw721-base minter can submit it.
+ SubmitCollection(SubmitCollection),
+ /// Curators only. Approval enables trading and passes the fee to the
+ /// treasury; rejection refunds it.
+ ReviewCollection { address:String, approve:bool, verified:bool },
+ SetCollectionVerified { address:String, verified:bool },
+ SetCollectionTrading { address:String, trading_enabled:bool },
+ SetCurators { curators:Vec<String> },
+ SetCollectionFee { fee:Option<Asset> },
+ SetModerators { moderators:Vec<String> },
+ /// Owner only: returns a token that reached the contract outside of a hook.
+ RescueNft { nft_address:String, token_id:String, recipient:String },
+ /// Owner only: sends native coins that no offering, swap or claim is owed.
+ RescueCoins { denom:String, amount:Uint128, recipient:String },
+ /// Owner only: sends cw20 tokens that no offering, swap or claim is owed.
+ RescueCw20 { token_address:String, amount:Uint128, recipient:String },
+ /// Keeps the proceeds of all the sender's sales for `WithdrawProceeds`.
+ SetPullProceeds { pull:bool },
+ WithdrawProceeds { asset:Asset },
+ /// Pays out sale proceeds in `denom` whose transfer to the sender failed.
+ ClaimBalance { denom:String },
+ /// Subscribes a contract to `MarketplaceHookMsg` notifications.
+ AddHook { address:String },
+ RemoveHook { address:String },
+ /// Owner only. Registers a storefront run by `owner`.
+ CreateStorefront { owner:String, name:String, royalty_portion:Decimal, recipients:Vec<UserInfo>, allowed_collections:Vec<String> },
+ /// Storefront owner only. New fee terms apply to later listings.
+ UpdateStorefront { storefront_id:String, royalty_portion:Decimal, recipients:Vec<UserInfo>, allowed_collections:Vec<String> },
+ /// Registers a referrer with the portion of the platform fee it receives,
+ /// or unregisters it with `None`.
+ SetReferrer { address:String, share:Option<Decimal> },
+ /// Moderators only. Flagged tokens cannot be listed, bought or swapped and
+ /// their listings are frozen until cleared; sellers can still withdraw them.
+ FlagToken { nft_address:String, token_id:String, flagged:bool, reason:String },
+ /// Moderators only. Blocked addresses cannot list, buy or receive tokens.
+ FlagAddress { address:String, flagged:bool, reason:String },
  ChangeOwner{address:String},
  SetTokenAddress{address:String},
  SetNftAddress { address:String},
+ SetRemainderAddress { address:String },
+ SetTreasury { address:String },
+ SetMaxRoyaltyPortion { max_royalty_portion:Decimal },
+ /// Volume tiers for fees paid in `denom`. An empty list removes them.
+ SetFeeTiers { denom:String, tiers:Vec<FeeTier> },
+ /// Portion of the fee waived when paying with HOPE.
+ SetHopeDiscount { hope_discount:Decimal },
+ /// Length in seconds of a volume window; zero counts all volume.
+ SetVolumeWindow { window:u64 },
+ /// Gas each hook notification may use.
+ SetHookGasLimit { gas_limit:u64 },
+ /// Accepts listings priced in the native `denom` from `min_price` up.
+ SetDenom { denom:String, symbol:String, decimals:u8, min_price:Uint128 },
+ RemoveDenom { denom:String },
+ SetTimelockDelay { delay:u64 },
+ ApplyPendingChange { change_id:String },
+ CancelPendingChange { change_id:String },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -31,14 +129,58 @@ pub enum ExecuteMsg {
 pub enum QueryMsg {
     /// Returns a human-readable representation of the arbiter.
     GetStateInfo {},
-    GetOfferings{},
-    GetMembers{}
+    /// Public offerings, plus the ones reserved for `reserved_for` if given.
+    /// With `storefront` only the offerings listed through it.
+    GetOfferings{ reserved_for:Option<String>, storefront:Option<String> },
+    GetMembers{},
+    GetPendingChanges{},
+    /// An unfinished bundle.
+    GetBundle{ bundle_id:String },
+    GetSwap{ swap_id:String },
+    /// Fee rate `address` currently pays when buying with `asset`.
+    FeeFor{ address:String, asset:String },
+    /// Native denoms accepted for listings.
+    GetDenoms{},
+    GetCollection{ address:String },
+    /// Collections ordered by address, optionally only those with `status`.
+    GetCollections{ status:Option<CollectionStatus>, start_after:Option<String>, limit:Option<u32> },
+    GetCurators{},
+    GetModerators{},
+    GetHooks{},
+    /// Compares the funds and tokens held in escrow with what the contract owns.
+    Solvency{},
+    /// Proceeds `address` can withdraw.
+    GetProceeds{ address:String },
+    /// Failed payouts `address` can claim.
+    GetClaimable{ address:String },
+    GetStorefront{ storefront_id:String },
+    GetStorefronts{ start_after:Option<String>, limit:Option<u32> },
+    /// Share and earned totals of a referrer.
+    GetReferrer{ address:String },
+    /// Flag changes, oldest first.
+    GetFlagLog{ start_after:Option<u64>, limit:Option<u32> },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct ApprovalListing {
+    pub token_id: String,
+    pub list_price: Option<Asset>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct SellNft {
     pub list_price: Asset,
+    /// Only these buyers may buy the offering. Reserved offerings are hidden
+    /// from `GetOfferings` unless a reserved buyer is asked for.
+    pub reserved_for: Option<Vec<String>>,
+    /// Storefront the token is listed through.
+    pub storefront: Option<String>,
+    /// Further accepted prices, one per denom besides `list_price`.
+    pub alternative_prices: Option<Vec<Asset>>,
+    /// Keep the proceeds for `WithdrawProceeds` instead of sending them.
+    pub pull_proceeds: Option<bool>,
 }
 
 
@@ -46,4 +188,108 @@ pub struct SellNft {
 #[serde(rename_all = "snake_case")]
 pub struct BuyNft {
     pub offering_id: String,
+    pub recipient: Option<String>,
+    pub msg: Option<Binary>,
+    /// Frontend that sent the buyer. Registered referrers get a share of the
+    /// platform fee.
+    pub referrer: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct BuyMany {
+    pub offering_ids: Vec<String>,
+    pub max_total: Uint128,
+    pub skip_unavailable: bool,
+}
+
+/// Payloads accepted through the cw20 `Receive` hook. A bare `BuyNft` is
+/// accepted as well.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Cw20HookMsg {
+    BuyNft(BuyNft),
+    BuyMany(BuyMany),
+    /// Swap proposal with the sent tokens as sweetener.
+    ProposeSwap(ProposeSwap),
+    /// Collection submission paying the fee in HOPE.
+    SubmitCollection(SubmitCollection),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SubmitCollection {
+    pub address: String,
+    pub name: String,
+    pub description: String,
+}
+
+/// Query of cw721-base collections for the account allowed to mint, answered
+/// with `MinterResponse`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CollectionQueryMsg {
+    Minter {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MinterResponse {
+    pub minter: String,
+}
+
+/// Payloads accepted through the cw721 `ReceiveNft` hook. A bare `SellNft` is
+/// accepted as well.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Cw721HookMsg {
+    SellNft(SellNft),
+    DepositBundle { bundle_id: String },
+    /// Escrows a token offered in a swap proposal.
+    DepositSwap { swap_id: String },
+    /// Sends the wanted token and completes the swap.
+    AcceptSwap { swap_id: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ProposeSwap {
+    pub offered: Vec<NftItem>,
+    pub wanted: NftItem,
+    pub end_height: Option<u64>,
+    pub end_time: Option<u64>,
+}
+
+/// Message executed on every hook contract. Failing hooks are ignored.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MarketplaceHookMsg {
+    MarketplaceEvent(MarketplaceEvent),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MarketplaceEvent {
+    Listed {
+        offering_id: String,
+        seller: String,
+        items: Vec<NftItem>,
+        list_price: Asset,
+    },
+    Sold {
+        offering_id: String,
+        seller: String,
+        buyer: String,
+        items: Vec<NftItem>,
+        price: Asset,
+    },
+    Cancelled {
+        offering_id: String,
+        seller: String,
+        items: Vec<NftItem>,
+    },
+    SwapAccepted {
+        swap_id: String,
+        proposer: String,
+        counterparty: String,
+        offered: Vec<NftItem>,
+        wanted: NftItem,
+    },
 }
@@ -1,6 +1,7 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use crate::state::Asset;
+use cosmwasm_std::{Decimal, Uint128};
+use crate::state::{Asset, ConfigChange, Custody, NftItem, CollectionInfo, FlagRecord, Storefront};
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
 pub struct QueryOfferingsResult {
@@ -8,6 +9,15 @@ pub struct QueryOfferingsResult {
     pub token_id: String,
     pub list_price: Asset,
     pub seller: String,
+    pub royalty_portion: Decimal,
+    pub custody: Custody,
+    pub reserved_for: Vec<String>,
+    pub nft_address: String,
+    pub bundle: Vec<NftItem>,
+    pub alternative_prices: Vec<Asset>,
+    pub storefront: Option<String>,
+    pub storefront_portion: Decimal,
+    pub pull_proceeds: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -16,3 +26,118 @@ pub struct OfferingsResponse {
 }
 
 // THIS FILE SHOULD BE EXTRACTED TO ITS OWN PACKAGE PROJECT LIKE CW20 OR CW721
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct QueryPendingChangeResult {
+    pub change_id: String,
+    pub change: ConfigChange,
+    pub executable_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingChangesResponse {
+    pub changes: Vec<QueryPendingChangeResult>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeForResponse {
+    pub royalty_portion: Decimal,
+    pub volume: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct QueryDenomResult {
+    pub denom: String,
+    pub symbol: String,
+    pub decimals: u8,
+    pub min_price: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DenomsResponse {
+    pub denoms: Vec<QueryDenomResult>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct QueryCollectionResult {
+    pub address: String,
+    pub info: CollectionInfo,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CollectionsResponse {
+    pub collections: Vec<QueryCollectionResult>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CuratorsResponse {
+    pub curators: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ModeratorsResponse {
+    pub moderators: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct QueryFlagResult {
+    pub id: u64,
+    pub record: FlagRecord,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FlagLogResponse {
+    pub flags: Vec<QueryFlagResult>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReferrerResponse {
+    /// `None` when the referrer is not registered (anymore).
+    pub share: Option<Decimal>,
+    pub earned: Vec<Asset>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct QueryStorefrontResult {
+    pub id: String,
+    pub storefront: Storefront,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StorefrontsResponse {
+    pub storefronts: Vec<QueryStorefrontResult>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HooksResponse {
+    pub hooks: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ClaimableResponse {
+    pub balances: Vec<Asset>,
+}
+
+/// What the contract owes in one denom against what it holds.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FundsSolvency {
+    pub denom: String,
+    pub owed: Uint128,
+    pub held: Uint128,
+    pub shortfall: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SolvencyResponse {
+    pub solvent: bool,
+    pub funds: Vec<FundsSolvency>,
+    /// Escrowed tokens the contract no longer owns.
+    pub missing_nfts: Vec<NftItem>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ProceedsResponse {
+    /// Whether all of the seller's proceeds are kept for withdrawal.
+    pub pull: bool,
+    pub balances: Vec<Asset>,
+}
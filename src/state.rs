@@ -1,11 +1,49 @@
-use cosmwasm_std::{Uint128, Decimal,Addr};
+use cosmwasm_std::{Uint128, Decimal, Env};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use cw_storage_plus::{Item,Map};
+use cw_storage_plus::{Item,Map,U64Key};
 
 pub const CONFIG: Item<State> = Item::new("config_state");
 pub const MEMBERS : Item<Vec<UserInfo>> = Item::new("config_members");
 pub const OFFERINGS: Map<&str, Offering> = Map::new("offerings");
+pub const PENDING_CHANGES: Map<&str, PendingChange> = Map::new("pending_changes");
+pub const FEE_TIERS: Map<&str, Vec<FeeTier>> = Map::new("fee_tiers");
+pub const TRADER_VOLUMES: Map<(&str, &str), TraderVolume> = Map::new("trader_volumes");
+pub const COLLECTIONS: Map<&str, CollectionInfo> = Map::new("collections");
+pub const BUNDLES: Map<&str, Bundle> = Map::new("bundles");
+pub const SWAPS: Map<&str, Swap> = Map::new("swaps");
+pub const DENOMS: Map<&str, DenomInfo> = Map::new("denoms");
+pub const CURATORS: Item<Vec<String>> = Item::new("curators");
+pub const MODERATORS: Item<Vec<String>> = Item::new("moderators");
+/// (collection, token_id) -> reason
+pub const FLAGGED_TOKENS: Map<(&str, &str), String> = Map::new("flagged_tokens");
+/// address -> reason
+pub const BLOCKED_ADDRESSES: Map<&str, String> = Map::new("blocked_addresses");
+pub const FLAG_LOG: Map<U64Key, FlagRecord> = Map::new("flag_log");
+/// referrer -> portion of the platform fee they receive
+pub const REFERRERS: Map<&str, Decimal> = Map::new("referrers");
+pub const STOREFRONTS: Map<&str, Storefront> = Map::new("storefronts");
+/// (collection, token_id) -> offering listing the token
+pub const LISTED_TOKENS: Map<(&str, &str), String> = Map::new("listed_tokens");
+/// offering id -> sale waiting for its token deliveries
+pub const SETTLEMENTS: Map<&str, Settlement> = Map::new("settlements");
+/// reply id -> what a settlement submessage was for
+pub const PENDING_REPLIES: Map<U64Key, PendingReply> = Map::new("pending_replies");
+pub const REPLY_SEQ: Item<u64> = Item::new("reply_seq");
+/// (address, denom) -> payouts that failed and can be claimed
+pub const CLAIMABLE: Map<(&str, &str), Uint128> = Map::new("claimable");
+/// (seller, denom) -> sale proceeds kept for the seller to withdraw
+pub const PROCEEDS: Map<(&str, &str), Uint128> = Map::new("proceeds");
+/// denom -> sum of the proceeds balances in it
+pub const PROCEEDS_TOTALS: Map<&str, Uint128> = Map::new("proceeds_totals");
+/// Sellers whose proceeds are always kept for withdrawal.
+pub const PULL_PROCEEDS: Map<&str, bool> = Map::new("pull_proceeds");
+/// denom -> sum of the claimable balances in it
+pub const CLAIMABLE_TOTALS: Map<&str, Uint128> = Map::new("claimable_totals");
+/// Contracts notified of marketplace events.
+pub const HOOKS: Item<Vec<String>> = Item::new("hooks");
+/// (referrer, denom) -> total earned
+pub const REFERRER_EARNINGS: Map<(&str, &str), Uint128> = Map::new("referrer_earnings");
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct State {
@@ -13,7 +51,22 @@ pub struct State {
     pub token_address:String,
     pub nft_address:String,
     pub offering_id:u64,
-    pub royalty_portion:Decimal
+    pub royalty_portion:Decimal,
+    pub remainder_address:String,
+    pub max_royalty_portion:Decimal,
+    pub treasury:String,
+    pub timelock_delay:u64,
+    pub change_id:u64,
+    pub hope_discount:Decimal,
+    pub volume_window:u64,
+    pub bundle_id:u64,
+    pub swap_id:u64,
+    /// Charged for collection submissions; free when unset.
+    pub collection_fee:Option<Asset>,
+    pub flag_id:u64,
+    pub storefront_id:u64,
+    /// Gas each hook notification may use before it fails on its own.
+    pub hook_gas_limit:u64,
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
@@ -21,6 +74,58 @@ pub struct Offering {
     pub token_id: String,
     pub seller: String,
     pub list_price: Asset,
+    /// Platform fee active when the offering was listed; used at settlement.
+    pub royalty_portion: Decimal,
+    pub custody: Custody,
+    /// Buyers the offering is reserved for; anyone may buy when empty.
+    pub reserved_for: Vec<String>,
+    /// Collection of `token_id`.
+    pub nft_address: String,
+    /// Every token of a bundle offering, including `token_id`. Empty for
+    /// single tokens.
+    pub bundle: Vec<NftItem>,
+    /// Other accepted prices, at most one per denom.
+    pub alternative_prices: Vec<Asset>,
+    /// Storefront the offering was listed through.
+    pub storefront: Option<String>,
+    /// Storefront fee active when the offering was listed, charged on top of
+    /// the platform fee.
+    pub storefront_portion: Decimal,
+    /// Keep the seller's proceeds for withdrawal instead of sending them.
+    pub pull_proceeds: bool,
+}
+
+impl Offering {
+    /// Price of the offering in `denom`, if the seller accepts it.
+    pub fn price_in(&self, denom: &str) -> Option<&Asset> {
+        std::iter::once(&self.list_price)
+            .chain(self.alternative_prices.iter())
+            .find(|price| price.denom == denom)
+    }
+
+    /// Tokens the buyer receives.
+    pub fn items(&self) -> Vec<NftItem> {
+        if self.bundle.is_empty() {
+            return vec![NftItem {
+                nft_address: self.nft_address.clone(),
+                token_id: self.token_id.clone(),
+            }];
+        }
+        self.bundle.clone()
+    }
+
+    pub fn reserved_for_buyer(&self, buyer: &str) -> bool {
+        self.reserved_for.is_empty() || self.reserved_for.iter().any(|address| address == buyer)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Custody {
+    /// The marketplace holds the token.
+    Escrow,
+    /// The seller holds the token and approved the marketplace to transfer it.
+    Approval,
 }
 
 
@@ -34,5 +139,179 @@ pub struct Asset {
 #[serde(rename_all = "snake_case")]
 pub struct UserInfo {
     pub address: String,
+    /// Share weight; the member receives `portion / sum of portions` of the fee.
     pub portion:Decimal
 }
+
+/// Config changes that have to wait `State.timelock_delay` before applying.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigChange {
+    RoyaltyPortion { royalty_portion: Decimal },
+    MaxRoyaltyPortion { max_royalty_portion: Decimal },
+    TokenAddress { address: String },
+    NftAddress { address: String },
+    RemainderAddress { address: String },
+    Treasury { address: String },
+    Members { members: Vec<UserInfo> },
+    AddMember { address: String, portion: Decimal },
+    RemoveMember { address: String },
+    UpdateMemberPortion { address: String, portion: Decimal },
+    TimelockDelay { delay: u64 },
+    FeeTiers { denom: String, tiers: Vec<FeeTier> },
+    HopeDiscount { hope_discount: Decimal },
+    VolumeWindow { window: u64 },
+    HookGasLimit { gas_limit: u64 },
+    RegisterCollection { address: String },
+    DeregisterCollection { address: String },
+    SetDenom { denom: String, info: DenomInfo },
+    RemoveDenom { denom: String },
+    Curators { curators: Vec<String> },
+    CollectionFee { fee: Option<Asset> },
+    Moderators { moderators: Vec<String> },
+    /// `None` unregisters the referrer.
+    Referrer { address: String, share: Option<Decimal> },
+    AddHook { address: String },
+    RemoveHook { address: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingChange {
+    pub change: ConfigChange,
+    /// Block time in seconds from which the change can be applied.
+    pub executable_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeTier {
+    /// Rolling volume a trader needs to reach this tier.
+    pub min_volume: Uint128,
+    pub royalty_portion: Decimal,
+}
+
+/// Volume of one trader in one denom, bucketed by `State.volume_window`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct TraderVolume {
+    pub window: u64,
+    pub current: Uint128,
+    pub previous: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NftItem {
+    pub nft_address: String,
+    pub token_id: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CollectionInfo {
+    pub name: String,
+    pub description: String,
+    pub creator: String,
+    /// Set by curators for collections they vouch for.
+    pub verified: bool,
+    /// Whether the collection can be listed.
+    pub trading_enabled: bool,
+    pub status: CollectionStatus,
+    /// Registration fee held until the submission is reviewed.
+    pub fee_paid: Option<Asset>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CollectionStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+/// A bundle waiting for its tokens to be escrowed.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Bundle {
+    pub seller: String,
+    pub items: Vec<BundleItem>,
+    pub list_price: Asset,
+    pub reserved_for: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BundleItem {
+    pub item: NftItem,
+    pub deposited: bool,
+}
+
+/// An offer to trade escrowed tokens and an optional sweetener for `wanted`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Swap {
+    pub proposer: String,
+    pub offered: Vec<BundleItem>,
+    pub sweetener: Option<Asset>,
+    pub wanted: NftItem,
+    pub end_height: Option<u64>,
+    pub end_time: Option<u64>,
+}
+
+impl Swap {
+    pub fn is_expired(&self, env: &Env) -> bool {
+        self.end_height.map_or(false, |height| env.block.height >= height)
+            || self.end_time.map_or(false, |time| env.block.time.seconds() >= time)
+    }
+}
+
+/// A native denom listings may be priced in.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DenomInfo {
+    pub symbol: String,
+    pub decimals: u8,
+    /// Lowest accepted listing price, in the base denom.
+    pub min_price: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FlagTarget {
+    Token { nft_address: String, token_id: String },
+    Address { address: String },
+}
+
+/// One moderator decision to flag or clear a token or address.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FlagRecord {
+    pub target: FlagTarget,
+    pub flagged: bool,
+    pub reason: String,
+    pub moderator: String,
+    pub height: u64,
+}
+
+/// A partner marketplace listing into the shared escrow under its own fee.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Storefront {
+    pub owner: String,
+    pub name: String,
+    pub royalty_portion: Decimal,
+    /// Weighted split of the storefront fee, like `MEMBERS`.
+    pub recipients: Vec<UserInfo>,
+    /// Collections that can be listed through the storefront; any accepted
+    /// collection when empty.
+    pub allowed_collections: Vec<String>,
+}
+
+/// A sale whose token deliveries have not all been confirmed yet. It is
+/// recorded in the volume and referral totals once they are.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Settlement {
+    pub buyer: String,
+    pub seller: String,
+    pub price: Asset,
+    pub referrer: Option<String>,
+    pub referrer_earned: Uint128,
+    pub deliveries_left: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PendingReply {
+    Delivery { offering_id: String },
+    Payout { recipient: String, asset: Asset },
+}